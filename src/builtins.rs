@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use crate::types::prelude::*;
+
+const LOGO: &str = r"   ___                     ___             __  ___  ____
+  / _ \_______ ___ ___ _  / _ )___ _______/ / / _ \/ __/
+ / // / __/ -_) _ `/  ' \/ _  / -_) __/ _  / / , _/\ \
+/____/_/  \__/\_,_/_/_/_/____/\__/_/  \_,_/ /_/|_/___/";
+
+/// Declare the standard library (what used to live in `examples/std.db`) in `state`
+pub fn register(state: &mut State) {
+    let undefined = state.undefined.clone();
+    // missing arguments are `undefined`, like they are for DreamBerd functions
+    let first = move |args: &[Pointer]| args.first().unwrap_or(&undefined).clone();
+
+    let arg = first.clone();
+    state.register_native("print", move |args| {
+        let text = args.iter().map(ToString::to_string).collect::<Vec<_>>();
+        println!("{}", text.join(" "));
+        Ok(arg(args))
+    });
+    let arg = first.clone();
+    state.register_native("str", move |args| Ok(Pointer::from(arg(args).to_string())));
+    let arg = first.clone();
+    state.register_native("identity", move |args| Ok(arg(args)));
+    let arg = first.clone();
+    state.register_native("bool", move |args| {
+        Ok(Pointer::from(arg(args).with_ref(Value::bool)))
+    });
+    let undefined = state.undefined.clone();
+    state.register_native("db", move |_| {
+        println!("{LOGO}");
+        Ok(undefined.clone())
+    });
+    let budget = state.budget.clone();
+    state.register_native("use", move |args| {
+        budget.borrow_mut().allocate()?;
+        Ok(make_use(&first(args)))
+    });
+}
+
+/// Make a `use` object: calling it returns the current value, and calling it with a value also sets it
+#[allow(clippy::mutable_key_type)]
+fn make_use(initial: &Pointer) -> Pointer {
+    let value = initial.convert(VarType::VarVar);
+    let inner = value.clone();
+    let call = NativeFunction::new("use", move |args| {
+        let previous = Pointer::from(inner.clone_inner());
+        if let Some(new) = args.first() {
+            if !new.with_ref(|new| *new == Value::empty_object()) {
+                inner.assign(new)?;
+            }
+        }
+        Ok(previous)
+    });
+    let mut obj = BTreeMap::new();
+    obj.insert(Value::from("value"), value);
+    obj.insert(Value::from("call"), Pointer::from(Value::Native(call)));
+    Pointer::from(Value::Object(obj)).convert(VarType::ConstVar)
+}
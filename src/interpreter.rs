@@ -1,12 +1,123 @@
-use std::rc::Rc;
+use std::{borrow::Cow, fs, path::Path, rc::Rc};
 
 use crate::types::prelude::*;
 
+#[cfg(test)]
 pub fn interpret(src: &Syntax) -> SResult<Pointer> {
     inner_interpret(src, rc_mut_new(State::new()))
 }
 
+/// Run a whole source file in `state`. If it's split into several files with `=====` lines, only the first one runs
+/// in `state`, and the rest each get their own global state.
+pub fn run_source(source: &str, state: &RcMut<State>) -> SResult<Pointer> {
+    // parse everything first, so a mistake in a later file stops the run before anything happens
+    let budget = state.borrow().budget.clone();
+    let files = crate::lexer::split_files(crate::lexer::tokenize(source)?)
+        .into_iter()
+        .map(|(name, tokens)| {
+            Ok((
+                name,
+                crate::parser::parse(source, tokens, &budget.borrow())?,
+            ))
+        })
+        .collect::<SResult<Vec<_>>>()?;
+    let mut files = files.into_iter();
+    let Some((_, first)) = files.next() else {
+        unreachable!("there's always at least one file")
+    };
+    let mut last = interpret_in(&first, state)?;
+    for (name, syntax) in files {
+        let (path, file_state) = {
+            let state = state.borrow();
+            // unnamed files count as part of the file they're written in
+            let path = name.map_or_else(|| state.path.to_path_buf(), |name| state.resolve(&name));
+            let file_state = State::for_file(path.clone(), &state);
+            (path, file_state)
+        };
+        let modules = file_state.modules.clone();
+        modules.borrow_mut().start(&path);
+        let result = interpret_in(&syntax, &rc_mut_new(file_state));
+        modules.borrow_mut().finish(&path, result.is_ok());
+        last = result?;
+    }
+    Ok(last)
+}
+
+/// Run `src` directly in `state` instead of in a new scope, so its declarations stick around. Its lines come after
+/// the ones that already ran in `state`.
+pub fn interpret_in(src: &Syntax, state: &RcMut<State>) -> SResult<Pointer> {
+    let statements = match src {
+        Syntax::Block(statements) => statements.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    let first = state.borrow().line;
+    let mut last = state.borrow().undefined.clone();
+    for statement in hoist(statements).iter() {
+        reach_line(statement, state, first);
+        last = inner_interpret(statement, state.clone())?;
+    }
+    Ok(last)
+}
+
+/// Move `state` to the source line `statement` is on, counting from `first`, so lifetimes in lines run out
+fn reach_line(statement: &Syntax, state: &RcMut<State>, first: usize) {
+    if let Syntax::Line(line, _) = statement {
+        state.borrow_mut().line = first + line;
+    }
+}
+
+/// Move declarations with negative lifetimes, like `const const name<-1> = 1!`, that many lines earlier in the block,
+/// so they can be used before they're written
+fn hoist(statements: &[Syntax]) -> Cow<'_, [Syntax]> {
+    if !statements.iter().any(|syn| hoisted_to(syn).is_some()) {
+        return Cow::Borrowed(statements);
+    }
+    let mut order: Vec<_> = statements
+        .iter()
+        .map(|syn| match (hoisted_to(syn), syn) {
+            (Some(line), Syntax::Line(_, inner)) => {
+                (line, false, Syntax::Line(line, inner.clone()))
+            }
+            (_, Syntax::Line(line, _)) => (*line, true, syn.clone()),
+            _ => (0, true, syn.clone()),
+        })
+        .collect();
+    // a hoisted declaration goes right before the statements on the line it was moved to, and the rest keep their
+    // order
+    order.sort_by_key(|&(line, stays, _)| (line, stays));
+    Cow::Owned(order.into_iter().map(|(_, _, syn)| syn).collect())
+}
+
+/// The line a declaration should be moved up to, if it has a negative lifetime
+fn hoisted_to(syn: &Syntax) -> Option<usize> {
+    let Syntax::Line(line, inner) = syn else {
+        return None;
+    };
+    hoisted_by(inner).map(|lines| line.saturating_sub(lines))
+}
+
+/// How many lines a declaration should be moved up by, if it has a negative lifetime
+fn hoisted_by(syn: &Syntax) -> Option<usize> {
+    match syn {
+        Syntax::Statement(_, inner, _) => hoisted_by(inner),
+        Syntax::Declare(_, _, Some(Lifetime::Lines(lines)), _, _) if *lines < 0 => {
+            Some(usize::try_from(lines.unsigned_abs()).unwrap_or(usize::MAX))
+        }
+        _ => None,
+    }
+}
+
 pub fn inner_interpret(src: &Syntax, state: RcMut<State>) -> SResult<Pointer> {
+    let budget = state.borrow().budget.clone();
+    budget.borrow_mut().step()?;
+    // whatever's inside of `src` runs further down the Rust stack
+    budget.borrow_mut().nest()?;
+    let result = interpret_nested(src, state, &budget);
+    budget.borrow_mut().unnest();
+    result
+}
+
+fn interpret_nested(src: &Syntax, state: RcMut<State>, budget: &RcMut<Budget>) -> SResult<Pointer> {
     match src {
         Syntax::Statement(false, content, _) => {
             inner_interpret(content, state.clone())?;
@@ -24,52 +135,119 @@ pub fn inner_interpret(src: &Syntax, state: RcMut<State>) -> SResult<Pointer> {
             }
             Ok(evaluated)
         }
+        // the block running it has already moved to its line
+        Syntax::Line(_, content) => interpret_nested(content, state, budget),
         Syntax::Negate(content) => {
             let evaluated = inner_interpret(content, state)?;
             Ok(-evaluated)
         }
-        Syntax::Operation(lhs, op, rhs) => interpret_operation(lhs, *op, rhs, state),
+        Syntax::Increment(is_prefix, content) => {
+            interpret_increment(*is_prefix, content, Operation::AddEq, &state)
+        }
+        Syntax::Decrement(is_prefix, content) => {
+            interpret_increment(*is_prefix, content, Operation::SubEq, &state)
+        }
+        Syntax::Operation(lhs, op, rhs, span) => {
+            interpret_operation(lhs, *op, rhs, state).map_err(|err| err.or_span(*span))
+        }
         Syntax::Block(statements) => {
             let state = rc_mut_new(State::from_parent(state));
+            let statements = hoist(statements);
             let mut iter = statements.iter();
             let Some(last) = iter.next_back() else {
-                return Ok(state.borrow().undefined.clone())
+                return Ok(state.borrow().undefined.clone());
             };
             for syn in iter {
+                reach_line(syn, &state, 0);
                 inner_interpret(syn, state.clone())?;
             }
+            reach_line(last, &state, 0);
             let res = inner_interpret(last, state)?;
             Ok(res)
         }
-        Syntax::Declare(var_type, ident, value) => {
-            let val = inner_interpret(value, state.clone())?;
-            state
-                .borrow_mut()
-                .insert(ident.clone(), val.convert(*var_type));
-            // println!("{state:#?}");
-            Ok(state.borrow().undefined.clone())
-        }
-        Syntax::String(str) => {
-            let mut string_buf = String::new();
-            for segment in str {
-                match segment {
-                    StringSegment::Ident(ident) => {
-                        string_buf.push_str(&state.borrow_mut().get(ident.clone()).to_string());
-                    }
-                    StringSegment::String(str) => string_buf.push_str(str),
-                }
-            }
-            Ok(Pointer::from(string_buf.as_ref()))
+        Syntax::Declare(var_type, ident, lifetime, value, span) => {
+            interpret_declare(*var_type, ident, *lifetime, value, *span, &state)
         }
-        Syntax::Call(func, args) => {
-            let func = state.borrow_mut().get(func.clone());
-            interpret_function(&func, args, state)
+        Syntax::String(str) => Ok(interpret_string(str, &state)),
+        Syntax::Call(callee, args, span) => {
+            interpret_call(callee, args, state).map_err(|err| err.or_span(*span))
         }
         Syntax::Ident(ident) => Ok(state.borrow_mut().get(ident.clone())),
-        Syntax::Function(args, body) => {
-            Ok(Pointer::from(Value::Function(args.clone(), *body.clone())))
+        Syntax::Array(items) => {
+            budget.borrow_mut().allocate()?;
+            let items = items
+                .iter()
+                .map(|item| Ok(inner_interpret(item, state.clone())?.convert(VarType::VarVar)))
+                .collect::<SResult<_>>()?;
+            Ok(Pointer::from(Value::Array(items)))
         }
+        Syntax::Object(fields) => {
+            budget.borrow_mut().allocate()?;
+            interpret_object(fields, &state)
+        }
+        Syntax::Index(target, idx, _) => {
+            let target = inner_interpret(target, state.clone())?;
+            // take the key out first, since `target` could be the same object and get borrowed again
+            let idx = inner_interpret(idx, state.clone())?.clone_inner();
+            growing(&target, &state, || target.index(&idx))
+        }
+        Syntax::Export(name, target) => interpret_export(name, target, &state),
+        Syntax::Import(name, span) => interpret_import(name, *span, &state),
+        Syntax::Function(args, body) => Ok(Pointer::from(Value::Function(Function::new(
+            args.clone(),
+            *body.clone(),
+            state,
+        )))),
+    }
+}
+
+fn interpret_declare(
+    var_type: VarType,
+    ident: &Rc<str>,
+    lifetime: Option<Lifetime>,
+    value: &Syntax,
+    span: Span,
+    state: &RcMut<State>,
+) -> SResult<Pointer> {
+    let val = inner_interpret(value, state.clone())?.convert(var_type);
+    if var_type == VarType::ConstConstConst {
+        state
+            .borrow()
+            .insert_global(ident.clone(), val)
+            .map_err(|err| Diagnostic::from(err).with_span(span))?;
+    } else {
+        state
+            .borrow_mut()
+            .insert_with_lifetime(ident.clone(), val, lifetime);
     }
+    // println!("{state:#?}");
+    Ok(state.borrow().undefined.clone())
+}
+
+/// Make an object out of a literal like `{a: 1, b: 2}`
+fn interpret_object(fields: &[(Rc<str>, Syntax)], state: &RcMut<State>) -> SResult<Pointer> {
+    #[allow(clippy::mutable_key_type)]
+    let mut obj = std::collections::BTreeMap::new();
+    for (key, value) in fields {
+        // the same as `obj.key = value`, without having to start from `{}`
+        let value = inner_interpret(value, state.clone())?.convert(VarType::VarVar);
+        obj.insert(Value::from(key.clone()), value);
+    }
+    Ok(Pointer::from(Value::Object(obj)))
+}
+
+/// Fill in the variables in a string
+fn interpret_string(segments: &[StringSegment], state: &RcMut<State>) -> Pointer {
+    let mut string_buf = String::new();
+    for segment in segments {
+        match segment {
+            StringSegment::Ident(ident) => {
+                string_buf.push_str(&state.borrow_mut().get(ident.clone()).to_string());
+            }
+            StringSegment::String(str) => string_buf.push_str(str),
+        }
+    }
+    Pointer::from(string_buf.as_ref())
 }
 
 fn interpret_operation(
@@ -78,151 +256,724 @@ fn interpret_operation(
     rhs: &Syntax,
     state: RcMut<State>,
 ) -> SResult<Pointer> {
-    let mut lhs_eval = inner_interpret(lhs, state.clone())?;
-    if let (Value::Object(_), Operation::Dot, Syntax::Ident(ident)) =
-        (&*lhs_eval.as_const(), op, rhs)
-    {
-        let inner_var = lhs_eval.as_var();
-        let Value::Object(ref mut obj) = *inner_var.borrow_mut() else { panic!("Internal Compiler Error at {}:{}", file!(), line!()) };
-        let key = Value::from(ident.clone());
-        if let Some(val) = obj.get(&key) {
-            // println!("{val:?}");
-            return Ok(val.clone());
-        }
-        let ptr = state.borrow().undefined.convert(VarType::VarVar);
-        // println!("{ptr:?}");
-        obj.insert(key, ptr.clone());
-        return Ok(ptr);
-    }
-    let rhs_eval = inner_interpret(rhs, state)?;
+    // the parser turns `->` into functions, but a hand-built `Syntax` might still have one
+    if op == Operation::Arrow {
+        let params = lhs.clone().into_params()?;
+        return Ok(Pointer::from(Value::Function(Function::new(
+            params,
+            rhs.clone(),
+            state,
+        ))));
+    }
+    if let (Operation::Equal(1), Syntax::Index(target, idx, _)) = (op, lhs) {
+        let target = inner_interpret(target, state.clone())?;
+        let idx = inner_interpret(idx, state.clone())?.clone_inner();
+        let rhs_eval = inner_interpret(rhs, state.clone())?;
+        growing(&target, &state, || target.set_index(&idx, &rhs_eval))??;
+        if let Some(name) = root_ident(lhs) {
+            run_watchers(name, &state)?;
+        }
+        return Ok(rhs_eval);
+    }
+    let lhs_eval = inner_interpret(lhs, state.clone())?;
+    if op == Operation::Dot {
+        return interpret_dot(&lhs_eval, rhs, &state);
+    }
+    let rhs_eval = inner_interpret(rhs, state.clone())?;
     // println!("{lhs:?} op {rhs:?}");
     // println!("{lhs_eval:?} op {rhs_eval:?}");
     match op {
-        Operation::Equal(1) => {
-            lhs_eval.assign(&rhs_eval)?;
-            Ok(rhs_eval)
-        }
+        Operation::Equal(1)
+        | Operation::AddEq
+        | Operation::SubEq
+        | Operation::MulEq
+        | Operation::DivEq
+        | Operation::ModEq => interpret_assign(lhs, lhs_eval, op, rhs_eval, &state),
         Operation::Equal(precision) => Ok(lhs_eval.eq(&rhs_eval, precision - 1)),
         Operation::Add => Ok(lhs_eval + rhs_eval),
         Operation::Sub => Ok(lhs_eval - rhs_eval),
         Operation::Mul => Ok(lhs_eval * rhs_eval),
         Operation::Div => Ok(lhs_eval / rhs_eval),
         Operation::Mod => Ok(lhs_eval % rhs_eval),
-        Operation::Dot => rhs_eval.with_ref(|rhs_eval| lhs_eval.dot(rhs_eval)),
         Operation::And => Ok(lhs_eval & rhs_eval),
         Operation::Or => Ok(lhs_eval | rhs_eval),
+        Operation::Lt => Ok(Pointer::from(lhs_eval < rhs_eval)),
+        Operation::Le => Ok(Pointer::from(lhs_eval <= rhs_eval)),
+        Operation::Gt => Ok(Pointer::from(lhs_eval > rhs_eval)),
+        Operation::Ge => Ok(Pointer::from(lhs_eval >= rhs_eval)),
+        Operation::Arrow => unreachable!("`->` is handled before evaluating either side"),
+        Operation::Dot => unreachable!("`.` is handled before evaluating the right side"),
+    }
+}
+
+/// Run `=` or an operator like `+=`, and remember the old value if that changed a variable
+fn interpret_assign(
+    lhs: &Syntax,
+    mut lhs_eval: Pointer,
+    op: Operation,
+    rhs_eval: Pointer,
+    state: &RcMut<State>,
+) -> SResult<Pointer> {
+    let old = match lhs {
+        Syntax::Ident(name) if changes(op, &lhs_eval) => Some((name, lhs_eval.as_const())),
+        _ => None,
+    };
+    let result = match op {
+        Operation::Equal(_) => {
+            lhs_eval.assign(&rhs_eval)?;
+            rhs_eval
+        }
         Operation::AddEq => {
             lhs_eval += rhs_eval;
-            Ok(lhs_eval)
+            lhs_eval
         }
         Operation::SubEq => {
             lhs_eval -= rhs_eval;
-            Ok(lhs_eval)
+            lhs_eval
         }
         Operation::MulEq => {
             lhs_eval *= rhs_eval;
-            Ok(lhs_eval)
+            lhs_eval
         }
         Operation::DivEq => {
             lhs_eval /= rhs_eval;
-            Ok(lhs_eval)
+            lhs_eval
         }
         Operation::ModEq => {
             lhs_eval %= rhs_eval;
-            Ok(lhs_eval)
+            lhs_eval
         }
-        Operation::Lt => Ok(Pointer::from(lhs_eval < rhs_eval)),
-        Operation::Le => Ok(Pointer::from(lhs_eval <= rhs_eval)),
-        Operation::Gt => Ok(Pointer::from(lhs_eval > rhs_eval)),
-        Operation::Ge => Ok(Pointer::from(lhs_eval >= rhs_eval)),
-        Operation::Arrow => todo!(),
-    }
-}
-
-fn interpret_function(func: &Pointer, args: &[Syntax], state: RcMut<State>) -> SResult<Pointer> {
-    func.with_ref(|func_eval|
-        match func_eval {
-            Value::Keyword(Keyword::If) => {
-                let [condition, body, ..] = args else {
-                        return Err(String::from("If statement requires two arguments: condition and body"))
-                    };
-                let condition_evaluated = inner_interpret(condition, state.clone())?;
-                // println!("{condition_evaluated:?}");
-                let bool = condition_evaluated.with_ref(Value::bool);
-                if bool == Boolean::True {
-                    inner_interpret(body, state)
-                } else if let (Boolean::Maybe, Some(body)) = (bool, args.get(3)) {
-                    inner_interpret(body, state)
-                } else {
-                    match args.get(2) {
-                        Some(else_statement) => inner_interpret(else_statement, state),
-                        None => Ok(state.borrow().undefined.clone())
-                    }
-                }
+        _ => unreachable!("only assignments are run by `interpret_assign`"),
+    };
+    if let Some((name, old)) = old {
+        record_change(name, old, state)?;
+    } else if let (Syntax::Operation(..) | Syntax::Index(..), Some(name)) = (lhs, root_ident(lhs)) {
+        // a field changed, so whatever's watching the object should know
+        run_watchers(name, state)?;
+    }
+    Ok(result)
+}
+
+/// Get the variable that `a.b[c]` and the like are fields of
+fn root_ident(syn: &Syntax) -> Option<&Rc<str>> {
+    match syn {
+        Syntax::Ident(name) => Some(name),
+        Syntax::Operation(target, Operation::Dot, _, _) | Syntax::Index(target, _, _) => {
+            root_ident(target)
+        }
+        _ => None,
+    }
+}
+
+/// `++` and `--`, which work like `+= 1` and `-= 1`
+fn interpret_increment(
+    is_prefix: bool,
+    content: &Syntax,
+    op: Operation,
+    state: &RcMut<State>,
+) -> SResult<Pointer> {
+    let evaluated = inner_interpret(content, state.clone())?;
+    let previous = Pointer::from(evaluated.clone_inner());
+    let updated = interpret_assign(content, evaluated, op, Pointer::from(1.0), state)?;
+    Ok(if is_prefix { updated } else { previous })
+}
+
+/// Whether `op` can change the variable `ptr` came from. Otherwise, it's an error or does nothing.
+const fn changes(op: Operation, ptr: &Pointer) -> bool {
+    match op {
+        Operation::Equal(_) | Operation::ModEq => {
+            matches!(ptr, Pointer::VarConst(_) | Pointer::VarVar(_))
+        }
+        _ => matches!(ptr, Pointer::ConstVar(_) | Pointer::VarVar(_)),
+    }
+}
+
+/// Remember what the variable `name` was before it changed, and call everything waiting for it in `next`
+fn record_change(name: &str, old: Rc<Value>, state: &RcMut<State>) -> SResult<()> {
+    let Some(history) = state.borrow().history(name) else {
+        return Ok(());
+    };
+    let waiting = history.borrow_mut().record(old);
+    if !waiting.is_empty() {
+        let new = Pointer::ConstConst(state.borrow_mut().get(name.into()).as_const());
+        let from = state.borrow().path.clone();
+        for callback in waiting {
+            call_with(&callback, std::slice::from_ref(&new), None, &from)?;
+        }
+    }
+    run_watchers(name, state)
+}
+
+/// Set up a `when` block, which runs `body` whenever a variable in `condition` changes and `condition` is true
+fn interpret_when(args: &[Syntax], state: &RcMut<State>) -> SResult<Pointer> {
+    let [condition, body] = args else {
+        return Err(Error::invalid_arguments(Keyword::When, args).into());
+    };
+    let watcher = Rc::new(Watcher::new(
+        condition.clone(),
+        body.clone(),
+        Scope::new(state.clone()),
+    ));
+    let state = state.borrow();
+    state.watchers.borrow_mut().push(watcher);
+    Ok(state.undefined.clone())
+}
+
+/// Run the `when` blocks watching the variable `name`. One that's already running is skipped, so a block that
+/// changes what it's watching (or sets off another block that does) doesn't go on forever.
+fn run_watchers(name: &str, state: &RcMut<State>) -> SResult<()> {
+    let Some(ptr) = state.borrow().lookup(name) else {
+        return Ok(());
+    };
+    let watchers = state.borrow().watchers.borrow().clone();
+    for watcher in watchers {
+        if !watcher.watches(&ptr) || !watcher.start() {
+            continue;
+        }
+        let result = run_watcher(&watcher);
+        watcher.finish();
+        // a `when` from another file points into that one, so leave the span for the change that set it off
+        result.map_err(|mut err| {
+            if *watcher.scope.0.borrow().path != *state.borrow().path {
+                err.span = None;
             }
-            Value::Keyword(Keyword::Delete) => {
-                if let [Syntax::Ident(key)] = args {
-                    state.borrow_mut().delete(key.clone());
-                }
-                Ok(state.borrow().undefined.clone())
+            err
+        })?;
+    }
+    Ok(())
+}
+
+fn run_watcher(watcher: &Watcher) -> SResult<()> {
+    let scope = watcher.scope.0.clone();
+    // running a block counts as a call, so blocks that make more blocks still hit the depth limit
+    let budget = scope.borrow().budget.clone();
+    budget.borrow_mut().enter()?;
+    let result = inner_interpret(&watcher.condition, scope.clone()).and_then(|condition| {
+        if condition.with_ref(Value::bool) == Boolean::True {
+            inner_interpret(&watcher.body, scope)?;
+        }
+        Ok(())
+    });
+    budget.borrow_mut().exit();
+    // the block runs wherever its variable happened to change, so it can't stop a loop or function there
+    result.map_err(|mut err| {
+        err.error = err.error.misplaced();
+        err
+    })
+}
+
+/// Look back at a variable with `previous`, take a snapshot of it with `current`, or wait for it to change with `next`
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn interpret_history(kw: Keyword, args: &[Syntax], state: &RcMut<State>) -> SResult<Pointer> {
+    let undefined = state.borrow().undefined.clone();
+    match (kw, args) {
+        (Keyword::Current, [value]) => Ok(Pointer::ConstConst(
+            inner_interpret(value, state.clone())?.as_const(),
+        )),
+        (Keyword::Previous, [Syntax::Ident(name), rest @ ..]) if rest.len() <= 1 => {
+            let steps = match rest {
+                [steps] => f64::try_from(inner_interpret(steps, state.clone())?.clone_inner())
+                    .unwrap_or(f64::NAN),
+                _ => 1.0,
+            };
+            let history = state.borrow().history(name);
+            let old = history
+                .filter(|_| steps >= 1.0 && steps.fract() == 0.0)
+                .and_then(|history| history.borrow().previous(steps as usize));
+            Ok(old.map_or(undefined, Pointer::ConstConst))
+        }
+        (Keyword::Next, [Syntax::Ident(name), callback]) => {
+            let callback = inner_interpret(callback, state.clone())?;
+            let history = state.borrow().history(name);
+            match history {
+                Some(history) => history.borrow_mut().wait(callback),
+                // a `const const const` never changes, so there's nothing to wait for
+                None if state.borrow().lookup(name).is_some() => {}
+                None => return Err(Error::NotDeclared(name.clone()).into()),
             }
-            Value::Keyword(Keyword::Function) => {
-                let [Syntax::Ident(name), args, body] = args else {
-                        return Err(format!("Invalid arguments for `function`: `{args:?}`; expected name, args, and body"))
-                    };
-                let args = match args {
-                    Syntax::Block(args) => args.clone(),
-                    other => vec![other.clone()],
-                };
-                let args: Vec<Rc<str>> = args
-                    .into_iter()
-                    .map(|syn| match syn {
-                        Syntax::Ident(str) => Ok(str),
-                        other => Err(format!("Invalid parameter name: `{other:?}`")),
-                    })
-                    .collect::<Result<_, _>>()?;
-                let inner_val = Value::Function(args, body.clone());
-                state
-                    .borrow_mut()
-                    .insert(name.clone(), Pointer::from(inner_val));
-                Ok(state.borrow().undefined.clone())
+            Ok(undefined)
+        }
+        _ => Err(Error::invalid_arguments(kw, args).into()),
+    }
+}
+
+/// Get `rhs` out of `lhs`. In `obj.key`, `key` is used as a string instead of being evaluated.
+fn interpret_dot(lhs: &Pointer, rhs: &Syntax, state: &RcMut<State>) -> SResult<Pointer> {
+    if let Syntax::Ident(ident) = rhs {
+        if lhs.with_ref(|lhs| matches!(lhs, Value::Object(_))) {
+            return growing(lhs, state, || lhs.field(&Value::from(ident.clone())));
+        }
+    }
+    let rhs = inner_interpret(rhs, state.clone())?.clone_inner();
+    growing(lhs, state, || lhs.dot(&rhs))?
+}
+
+/// Run `func`, which might add fields or elements to `target`, and count each one it adds as a new object
+fn growing<T>(target: &Pointer, state: &RcMut<State>, func: impl FnOnce() -> T) -> SResult<T> {
+    let len = || {
+        target.with_ref(|value| match value {
+            Value::Object(obj) => obj.len(),
+            Value::Array(arr) => arr.len(),
+            _ => 0,
+        })
+    };
+    let before = len();
+    let result = func();
+    let budget = state.borrow().budget.clone();
+    for _ in before..len() {
+        budget.borrow_mut().allocate()?;
+    }
+    Ok(result)
+}
+
+/// The result of running something in tail position: either its value, or a call to a DreamBerd function that still
+/// needs to be made. `call_with` makes those calls in a loop, so recursing at the end of a function doesn't use up the
+/// stack.
+enum Tail {
+    Value(Pointer),
+    Call {
+        func: Pointer,
+        args: Vec<Pointer>,
+        this: Option<Pointer>,
+        /// The call is the end of a `!` statement, so its result is thrown away
+        discard: bool,
+    },
+}
+
+/// Like `inner_interpret`, but a call at the very end of `src` (including through `if` branches) isn't made yet
+fn interpret_tail(src: &Syntax, state: RcMut<State>) -> SResult<Tail> {
+    match src {
+        Syntax::Block(statements) => {
+            let state = rc_mut_new(State::from_parent(state));
+            let statements = hoist(statements);
+            let Some((last, rest)) = statements.split_last() else {
+                return Ok(Tail::Value(state.borrow().undefined.clone()));
+            };
+            for syn in rest {
+                reach_line(syn, &state, 0);
+                inner_interpret(syn, state.clone())?;
             }
-            Value::Keyword(Keyword::Eval) => {
-                let [body] = args else {
-                    return Err(format!("You can only `eval` one thing at a time; got `{args:?}`"));
-                };
-                let text = inner_interpret(body, state.clone())?.to_string();
-                // #[cfg(debug_assertions)]
-                // println!("Evaluating Inner: {text}");
-                let tokens = crate::lexer::tokenize(&text)?;
-                // #[cfg(debug_assertions)]
-                // println!("Evaluating Tokens: {tokens:?}");
-                let syntax = crate::parser::parse(tokens)?;
-                // #[cfg(debug_assertions)]
-                // println!("Evaluating Syntax: {syntax:?}");
-                inner_interpret(&syntax, state)
+            reach_line(last, &state, 0);
+            interpret_tail(last, state)
+        }
+        Syntax::Line(_, content) => interpret_tail(content, state),
+        Syntax::Statement(false, content, _) => {
+            let tail = interpret_tail(content, state.clone())?;
+            let state = state.borrow();
+            match tail {
+                Tail::Value(_) => Ok(Tail::Value(state.undefined.clone())),
+                Tail::Call {
+                    func, args, this, ..
+                } => Ok(Tail::Call {
+                    func,
+                    args,
+                    this,
+                    discard: true,
+                }),
             }
-            Value::Object(obj) => {
-                let Some(call) = obj.get(&"call".into()) else {
-                    return Err(format!("`Object({obj:?})` is not a function"))
-                };
-                let mut new_state = State::from_parent(state);
-                new_state.insert("self".into(), func.clone());
-                interpret_function(call, args, rc_mut_new(new_state))
+        }
+        Syntax::Call(callee, args, span) => {
+            interpret_tail_call(callee, args, state).map_err(|err| err.or_span(*span))
+        }
+        other => inner_interpret(other, state).map(Tail::Value),
+    }
+}
+
+/// Call `callee`. If it's a method, like `obj.method(args)`, `self` is `obj` inside of it.
+fn interpret_call(callee: &Syntax, args: &[Syntax], state: RcMut<State>) -> SResult<Pointer> {
+    let from = state.borrow().path.clone();
+    match interpret_tail_call(callee, args, state)? {
+        Tail::Value(value) => Ok(value),
+        Tail::Call {
+            func, args, this, ..
+        } => call_with(&func, &args, this, &from),
+    }
+}
+
+/// Evaluate the function and its arguments, but leave calling DreamBerd functions to `call_with`
+fn interpret_tail_call(callee: &Syntax, args: &[Syntax], state: RcMut<State>) -> SResult<Tail> {
+    let (func, this) = if let Syntax::Operation(obj, Operation::Dot, key, _) = callee {
+        let receiver = inner_interpret(obj, state.clone())?;
+        (interpret_dot(&receiver, key, &state)?, Some(receiver))
+    } else {
+        (inner_interpret(callee, state.clone())?, None)
+    };
+    match &*func.as_const() {
+        Value::Function(..) => {
+            let args = args
+                .iter()
+                .map(|syn| inner_interpret(syn, state.clone()))
+                .collect::<SResult<_>>()?;
+            Ok(Tail::Call {
+                func,
+                args,
+                this,
+                discard: false,
+            })
+        }
+        Value::Keyword(Keyword::If) => match interpret_if(args, &state)? {
+            Some(branch) => interpret_tail(branch, state),
+            None => Ok(Tail::Value(state.borrow().undefined.clone())),
+        },
+        _ => interpret_function(&func, args, state, this).map(Tail::Value),
+    }
+}
+
+/// Call `func` with `args`, which are evaluated in the caller's `state`. `this` is what `self` means in the body.
+fn interpret_function(
+    func: &Pointer,
+    args: &[Syntax],
+    state: RcMut<State>,
+    this: Option<Pointer>,
+) -> SResult<Pointer> {
+    // don't hold a borrow of `func` while running it, since the body might modify it through `self`
+    match &*func.as_const() {
+        Value::Keyword(Keyword::If) => match interpret_if(args, &state)? {
+            Some(branch) => inner_interpret(branch, state),
+            None => Ok(state.borrow().undefined.clone()),
+        },
+        Value::Keyword(Keyword::While) => interpret_while(args, &state),
+        Value::Keyword(
+            kw @ (Keyword::Break | Keyword::Continue | Keyword::Return | Keyword::Throw),
+        ) => Err(interpret_signal(*kw, args, &state)?.into()),
+        Value::Keyword(Keyword::Try) => interpret_try(args, &state),
+        Value::Keyword(Keyword::When) => interpret_when(args, &state),
+        Value::Keyword(kw @ (Keyword::Previous | Keyword::Current | Keyword::Next)) => {
+            interpret_history(*kw, args, &state)
+        }
+        Value::Keyword(Keyword::Delete) => {
+            if let [Syntax::Ident(key)] = args {
+                state.borrow_mut().delete(key.clone());
+            }
+            Ok(state.borrow().undefined.clone())
+        }
+        Value::Keyword(Keyword::Function) => {
+            let [Syntax::Ident(name), args, body] = args else {
+                return Err(Error::invalid_arguments(Keyword::Function, args).into());
+            };
+            let inner_val = Value::Function(Function::new(
+                args.clone().into_params()?,
+                body.clone(),
+                state.clone(),
+            ));
+            state
+                .borrow_mut()
+                .insert(name.clone(), Pointer::from(inner_val));
+            Ok(state.borrow().undefined.clone())
+        }
+        Value::Keyword(Keyword::Eval) => {
+            // `eval` can call itself without calling a function, so it counts as a call too
+            let budget = state.borrow().budget.clone();
+            budget.borrow_mut().enter()?;
+            let result = interpret_eval(args, &state);
+            budget.borrow_mut().exit();
+            result
+        }
+        Value::Keyword(Keyword::Import) => {
+            let [file] = args else {
+                return Err(Error::invalid_arguments(Keyword::Import, args).into());
+            };
+            let file = inner_interpret(file, state.clone())?.to_string();
+            import_file(&file, &state)
+        }
+        Value::Object(_) => {
+            let (call, this) = follow_calls(func)?;
+            interpret_function(&call, args, state, this)
+        }
+        Value::Native(_) | Value::Function(..) => {
+            let args = args
+                .iter()
+                .map(|syn| inner_interpret(syn, state.clone()))
+                .collect::<SResult<Vec<_>>>()?;
+            let from = state.borrow().path.clone();
+            call_with(func, &args, this, &from)
+        }
+        _ => Err(Error::NotAFunction(func.clone()).into()),
+    }
+}
+
+/// Pick which argument of an `if` to run, if any
+fn interpret_if<'a>(args: &'a [Syntax], state: &RcMut<State>) -> SResult<Option<&'a Syntax>> {
+    let [condition, body, ..] = args else {
+        return Err(Error::invalid_arguments(Keyword::If, args).into());
+    };
+    let condition_evaluated = inner_interpret(condition, state.clone())?;
+    // println!("{condition_evaluated:?}");
+    let bool = condition_evaluated.with_ref(Value::bool);
+    if bool == Boolean::True {
+        Ok(Some(body))
+    } else if let (Boolean::Maybe, Some(body)) = (bool, args.get(3)) {
+        Ok(Some(body))
+    } else {
+        Ok(args.get(2))
+    }
+}
+
+/// Call a function with arguments that have already been evaluated, from code in the file at `from`
+fn call_with(
+    func: &Pointer,
+    args: &[Pointer],
+    this: Option<Pointer>,
+    from: &Path,
+) -> SResult<Pointer> {
+    match &*func.as_const() {
+        Value::Native(native) => native.call(args),
+        Value::Function(function) => {
+            let budget = function.scope().borrow().budget.clone();
+            budget.borrow_mut().enter()?;
+            let result = call_function(func, args, this, from);
+            budget.borrow_mut().exit();
+            result
+        }
+        Value::Object(_) => {
+            let (call, this) = follow_calls(func)?;
+            call_with(&call, args, this, from)
+        }
+        _ => Err(Error::NotAFunction(func.clone()).into()),
+    }
+}
+
+/// Follow the `call` field of the object `func`, and of the objects that leads to, until it gets to something else.
+/// Returns that, and the last object, which it's called on. This is a loop instead of recursion, since an object can
+/// call itself.
+///
+/// # Errors
+/// Returns `Error::NotAFunction` if an object doesn't have a `call` field, or if its `call` fields lead back to it
+fn follow_calls(func: &Pointer) -> SResult<(Pointer, Option<Pointer>)> {
+    let (mut func, mut this) = (func.clone(), None);
+    let mut seen: Vec<Pointer> = Vec::new();
+    loop {
+        let call = func.with_ref(|value| match value {
+            Value::Object(obj) => Some(obj.get(&"call".into()).cloned()),
+            _ => None,
+        });
+        let Some(call) = call else {
+            return Ok((func, this));
+        };
+        let Some(call) = call.filter(|_| !seen.iter().any(|seen| seen.shares_cell(&func))) else {
+            return Err(Error::NotAFunction(func).into());
+        };
+        seen.push(func.clone());
+        this = Some(core::mem::replace(&mut func, call));
+    }
+}
+
+/// Run a DreamBerd function, called from code in the file at `from`. Calls at the end of the body replace this one
+/// instead of going deeper.
+fn call_function(
+    func: &Pointer,
+    args: &[Pointer],
+    this: Option<Pointer>,
+    from: &Path,
+) -> SResult<Pointer> {
+    let (mut func, mut args, mut this) = (func.clone(), args.to_vec(), this);
+    let mut discard = false;
+    loop {
+        let value = func.as_const();
+        let Value::Function(function) = &*value else {
+            return call_with(&func, &args, this, from);
+        };
+        // the body runs where the function was made, not where it was called
+        let mut inner_state = State::from_parent(function.scope().clone());
+        if let Some(this) = this {
+            inner_state.insert("self".into(), this);
+        }
+        for (idx, ident) in function.params().iter().enumerate() {
+            let arg = args
+                .get(idx)
+                .cloned()
+                .unwrap_or_else(|| inner_state.undefined.clone());
+            inner_state.insert(ident.clone(), arg);
+        }
+        let undefined = inner_state.undefined.clone();
+        let value = match interpret_tail(function.body(), rc_mut_new(inner_state)) {
+            Ok(Tail::Value(value))
+            | Err(Diagnostic {
+                error: Error::Return(value),
+                ..
+            }) => value,
+            Ok(Tail::Call {
+                func: next,
+                args: next_args,
+                this: next_this,
+                discard: next_discard,
+            }) => {
+                (func, args, this) = (next, next_args, next_this);
+                discard |= next_discard;
+                continue;
             }
-            Value::Function(fn_args, body) => {
-                let mut inner_state = State::from_parent(state.clone());
-                for (idx, ident) in fn_args.iter().enumerate() {
-                    let arg_eval = if let Some(syn) = args.get(idx) {
-                        inner_interpret(syn, state.clone())?
-                    } else {
-                        state.borrow().undefined.clone()
-                    };
-                    inner_state.insert(ident.clone(), arg_eval);
+            // a loop outside of the function can't be stopped from inside of it
+            Err(mut err) => {
+                err.error = err.error.misplaced();
+                // its span points into the file the function was written in, so leave it for the call to fill in
+                if *function.scope().borrow().path != *from {
+                    err.span = None;
                 }
-                inner_interpret(body, rc_mut_new(inner_state))
+                return Err(err);
             }
-            other => Err(format!("`{other:?}` is not a function")),
+        };
+        return Ok(if discard { undefined } else { value });
+    }
+}
+
+/// Run a string as code, in the same scope as the `eval` call
+fn interpret_eval(args: &[Syntax], state: &RcMut<State>) -> SResult<Pointer> {
+    let [body] = args else {
+        return Err(Error::invalid_arguments(Keyword::Eval, args).into());
+    };
+    let text = inner_interpret(body, state.clone())?.to_string();
+    // #[cfg(debug_assertions)]
+    // println!("Evaluating Inner: {text}");
+    // spans inside `text` don't refer to the file, so the outer error points at the `eval` call
+    let wrap = |err| Diagnostic::new(Error::Eval(Box::new(err)));
+    let tokens = crate::lexer::tokenize(&text).map_err(wrap)?;
+    // #[cfg(debug_assertions)]
+    // println!("Evaluating Tokens: {tokens:?}");
+    let budget = state.borrow().budget.clone();
+    // limits still stop everything, even if it's the parser that goes over them
+    let syntax = crate::parser::parse(&text, tokens, &budget.borrow()).map_err(|mut err| {
+        if matches!(err.error, Error::LimitExceeded(..)) {
+            err.span = None;
+            err
+        } else {
+            wrap(err)
+        }
+    })?;
+    // #[cfg(debug_assertions)]
+    // println!("Evaluating Syntax: {syntax:?}");
+    // `break`, `continue`, `return`, and `throw` still work from inside of `eval`, and limits still stop everything
+    inner_interpret(&syntax, state.clone()).map_err(|mut err| {
+        if err.error.is_signal() || matches!(err.error, Error::Thrown(_) | Error::LimitExceeded(..))
+        {
+            // its span points into `text`, so leave it for the `eval` call to fill in
+            err.span = None;
+            err
+        } else {
+            wrap(err)
+        }
+    })
+}
+
+/// Get the error for `break()`, `continue()`, `return(value)`, or `throw(value)`, which gets passed up until it gets
+/// to the loop, function, or `try` it's for
+fn interpret_signal(kw: Keyword, args: &[Syntax], state: &RcMut<State>) -> SResult<Error> {
+    let value = match args {
+        [] => state.borrow().undefined.clone(),
+        [value] if matches!(kw, Keyword::Return | Keyword::Throw) => {
+            inner_interpret(value, state.clone())?
+        }
+        _ => return Err(Error::invalid_arguments(kw, args).into()),
+    };
+    Ok(match kw {
+        Keyword::Break => Error::Break,
+        Keyword::Continue => Error::Continue,
+        Keyword::Return => Error::Return(value),
+        _ => Error::Thrown(value),
+    })
+}
+
+/// Run the body, and if it fails, give the handler what went wrong: the thrown value, or the error message. If the
+/// handler isn't a function, it's used as the result instead.
+fn interpret_try(args: &[Syntax], state: &RcMut<State>) -> SResult<Pointer> {
+    let [body, handler] = args else {
+        return Err(Error::invalid_arguments(Keyword::Try, args).into());
+    };
+    let error = match inner_interpret(body, state.clone()) {
+        // `break`, `continue`, and `return` aren't errors, so they go right past. Neither does going over a limit, so
+        // sandboxed code can't ignore it.
+        Err(err) if !err.error.is_signal() && !matches!(err.error, Error::LimitExceeded(..)) => {
+            err.error
         }
-    )
+        result => return result,
+    };
+    let error = match error {
+        Error::Thrown(value) => value,
+        other => Pointer::from(Value::from(other.to_string())),
+    };
+    let handler = inner_interpret(handler, state.clone())?;
+    if handler.with_ref(|handler| matches!(handler, Value::Function(..) | Value::Native(_))) {
+        let from = state.borrow().path.clone();
+        call_with(&handler, &[error], None, &from)
+    } else {
+        Ok(handler)
+    }
+}
+
+/// Run the body while the condition is true, or the third argument while it's `maybe`. `break()` and `continue()`
+/// come back up as errors until they get here.
+fn interpret_while(args: &[Syntax], state: &RcMut<State>) -> SResult<Pointer> {
+    let [condition, body, ..] = args else {
+        return Err(Error::invalid_arguments(Keyword::While, args).into());
+    };
+    let mut last = state.borrow().undefined.clone();
+    loop {
+        let bool = inner_interpret(condition, state.clone())?.with_ref(Value::bool);
+        let body = match (bool, args.get(2)) {
+            (Boolean::True, _) => body,
+            (Boolean::Maybe, Some(maybe_body)) => maybe_body,
+            _ => return Ok(last),
+        };
+        match inner_interpret(body, state.clone()) {
+            Ok(value) => last = value,
+            Err(Diagnostic {
+                error: Error::Break,
+                ..
+            }) => return Ok(last),
+            Err(Diagnostic {
+                error: Error::Continue,
+                ..
+            }) => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Make `name` available to `import` in the file at `target`
+fn interpret_export(name: &Rc<str>, target: &Syntax, state: &RcMut<State>) -> SResult<Pointer> {
+    let ptr = state.borrow_mut().get(name.clone());
+    let target = inner_interpret(target, state.clone())?.to_string();
+    let state = state.borrow();
+    let to = state.resolve(&target);
+    state.modules.borrow_mut().export(&to, name.clone(), ptr);
+    Ok(state.undefined.clone())
+}
+
+/// Declare `name` from what was exported to this file
+fn interpret_import(name: &Rc<str>, span: Span, state: &RcMut<State>) -> SResult<Pointer> {
+    let imported = {
+        let state = state.borrow();
+        let modules = state.modules.borrow();
+        modules.import(&state.path, name)
+    };
+    let Some(ptr) = imported else {
+        return Err(Diagnostic::new(Error::NotExported(name.clone())).with_span(span));
+    };
+    state.borrow_mut().insert(name.clone(), ptr);
+    Ok(state.borrow().undefined.clone())
+}
+
+/// Run another file in its own state (unless it's already been run), then get everything it exported to this one
+fn import_file(file: &str, state: &RcMut<State>) -> SResult<Pointer> {
+    let (path, modules) = {
+        let state = state.borrow();
+        (state.resolve(file), state.modules.clone())
+    };
+    modules.borrow().check_import(&path)?;
+    let name: Rc<str> = path.display().to_string().into();
+    if modules.borrow().is_loading(&path) {
+        return Err(Error::ImportCycle(name).into());
+    }
+    if !modules.borrow().is_loaded(&path) {
+        let source =
+            fs::read_to_string(&path).map_err(|err| Error::Io(name.clone(), err.kind()))?;
+        modules.borrow_mut().start(&path);
+        let file_state = rc_mut_new(State::for_file(path.clone(), &state.borrow()));
+        // spans inside the other file don't refer to this one, so the outer error points at the `import` call
+        let result = run_source(&source, &file_state);
+        modules.borrow_mut().finish(&path, result.is_ok());
+        result.map_err(|err| Diagnostic::new(Error::Import(name, Box::new(err))))?;
+    }
+    let state = state.borrow();
+    let exports = modules.borrow().exports_to(&state.path);
+    Ok(Pointer::from(exports))
 }
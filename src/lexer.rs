@@ -1,18 +1,77 @@
-use std::iter::Peekable;
+use std::{cell::Cell, iter::Peekable, rc::Rc};
+
+use lazy_regex::regex_captures;
 
 use crate::types::prelude::*;
 
-pub fn tokenize(source: &str) -> SResult<Vec<Token>> {
-    let mut chars = source.chars().peekable();
-    let mut token_stream = Vec::new();
-    while chars.peek().is_some() {
-        if let Some(tok) = inner_tokenize(&mut chars)? {
-            token_stream.push(tok);
+pub fn tokenize(source: &str) -> SResult<Vec<(Token, Span)>> {
+    // byte offset of everything pulled out of `source`, including a peeked character
+    let offset = Cell::new(0);
+    let mut chars = source
+        .chars()
+        .inspect(|char| offset.set(offset.get() + char.len_utf8()))
+        .peekable();
+    let mut token_stream: Vec<(Token, Span)> = Vec::new();
+    while let Some(next) = chars.peek() {
+        let start = offset.get() - next.len_utf8();
+        if start == 0 || source[..start].ends_with('\n') {
+            if let Some((line, name)) = file_separator(&source[start..]) {
+                for _ in line.chars() {
+                    chars.next();
+                }
+                let name = (!name.is_empty()).then(|| name.into());
+                token_stream.push((Token::File(name), Span::new(start, start + line.len())));
+                continue;
+            }
+        }
+        let tok = inner_tokenize(&mut chars)
+            .map_err(|err| err.or_span(Span::new(start, offset.get())))?;
+        // peek first, so that `offset` includes the peeked character
+        let peeked = chars.peek().map_or(0, |char| char.len_utf8());
+        let end = offset.get() - peeked;
+        match (tok, token_stream.last_mut()) {
+            // whitespace on both sides of a comment is still one gap
+            (Some(Token::Space(count)), Some((Token::Space(prev), span))) => {
+                *prev = prev.saturating_add(count);
+                span.end = end;
+            }
+            (Some(tok), _) => token_stream.push((tok, Span::new(start, end))),
+            (None, _) => {}
         }
     }
     Ok(token_stream)
 }
 
+/// Match a line of five or more `=` at the start of `source`, optionally naming the next file like `===== add.db ==`.
+/// Returns the whole line and the name, which is empty if there isn't one.
+fn file_separator(source: &str) -> Option<(&str, &str)> {
+    regex_captures!(
+        r"^={5,}(?:[^\S\n]*([^\s=]+)[^\S\n]*=+)?[^\S\n]*(?:\n|$)",
+        source
+    )
+}
+
+/// The name of a file separated by `=====`, and its tokens
+type SourceFile = (Option<Rc<str>>, Vec<(Token, Span)>);
+
+/// Split a tokenized source file into the files separated by `=====` lines, each with the name it was given
+pub fn split_files(tokens: Vec<(Token, Span)>) -> Vec<SourceFile> {
+    let mut files = Vec::new();
+    let (mut name, mut file) = (None, Vec::new());
+    for (tok, span) in tokens {
+        if let Token::File(next) = tok {
+            files.push((
+                core::mem::replace(&mut name, next),
+                core::mem::take(&mut file),
+            ));
+        } else {
+            file.push((tok, span));
+        }
+    }
+    files.push((name, file));
+    files
+}
+
 macro_rules! multi_character_pattern {
     ($chars:ident $just:expr; {$($char:expr => $eq:expr),*}) => {
         match $chars.peek() {
@@ -73,11 +132,7 @@ fn lex_string<T: Iterator<Item = char>>(chars: &mut Peekable<T>, end: char) -> S
             }
         } else if next == '\\' {
             string_buf.push(next);
-            string_buf.push(
-                chars
-                    .next()
-                    .ok_or_else(|| String::from("Unexpected end of file"))?,
-            );
+            string_buf.push(chars.next().ok_or(Error::UnexpectedEof)?);
         } else {
             string_buf.push(next);
         }
@@ -88,22 +143,41 @@ fn lex_string<T: Iterator<Item = char>>(chars: &mut Peekable<T>, end: char) -> S
     Ok(Token::String(outer_buf))
 }
 
+/// skip a `//` line comment or a `/* */` block comment, starting after the first `/`
+fn skip_comment<T: Iterator<Item = char>>(chars: &mut Peekable<T>) -> SResult<()> {
+    match chars.next() {
+        Some('/') => {
+            while chars.next_if(|&char| char != '\n').is_some() {}
+            Ok(())
+        }
+        Some('*') => {
+            while let Some(next) = chars.next() {
+                if next == '*' && chars.next_if_eq(&'/').is_some() {
+                    return Ok(());
+                }
+            }
+            Err(Error::Expected("`*/` to close this comment", None).into())
+        }
+        _ => unreachable!(),
+    }
+}
+
 fn count_char<T: Iterator<Item = char>, F: Fn(u8) -> Token>(
     chars: &mut Peekable<T>,
     tok: char,
     typ: F,
 ) -> Token {
-    let mut count = 1;
+    let mut count: u8 = 1;
     while chars.peek() == Some(&tok) {
         chars.next();
-        count += 1;
+        count = count.saturating_add(1);
     }
     typ(count)
 }
 
 fn inner_tokenize<T: Iterator<Item = char>>(chars: &mut Peekable<T>) -> SResult<Option<Token>> {
     let Some(char) = chars.next() else {
-        return Err(String::from("Unexpected end of file"))
+        return Err(Error::UnexpectedEof.into());
     };
     Ok(Some(match char {
         '{' => Token::LSquirrely,
@@ -125,6 +199,10 @@ fn inner_tokenize<T: Iterator<Item = char>>(chars: &mut Peekable<T>) -> SResult<
             multi_character_pattern!(chars Token::Tack; {'=' => Token::TackEq, '>' => Token::Arrow, '-' => Token::TackTack})
         }
         '*' => multi_character_pattern!(chars Token::Star; {'=' => Token::StarEq}),
+        '/' if matches!(chars.peek(), Some('/' | '*')) => {
+            skip_comment(chars)?;
+            return Ok(None);
+        }
         '/' => multi_character_pattern!(chars Token::Slash; {'=' => Token::SlashEq}),
         '%' => multi_character_pattern!(chars Token::Percent; {'=' => Token::PercentEq}),
         '<' => multi_character_pattern!(chars Token::LCaret; {'=' => Token::LCaretEq}),
@@ -140,14 +218,14 @@ fn inner_tokenize<T: Iterator<Item = char>>(chars: &mut Peekable<T>) -> SResult<
         '?' => count_char(chars, '?', Token::Question),
         _ => {
             if char.is_whitespace() {
-                let mut whitespace_count = 1;
+                let mut whitespace_count: u8 = 1;
                 while let Some(tok) = chars.peek() {
                     if tok.is_whitespace() {
                         // `'\n'` counts as multiple whitespaces
-                        whitespace_count += match tok {
+                        whitespace_count = whitespace_count.saturating_add(match tok {
                             '\n' => 3,
                             _ => 1,
-                        };
+                        });
                         chars.next();
                     } else {
                         break;
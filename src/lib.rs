@@ -0,0 +1,178 @@
+#![warn(clippy::pedantic, clippy::nursery)]
+#![allow(clippy::must_use_candidate)]
+#![warn(unnameable_types)]
+//! Rust-based interpreter for the DreamBerd language.
+//!
+//! ```
+//! use dreamberd_rs::{Interpreter, Value};
+//!
+//! let interpreter = Interpreter::new();
+//! interpreter.set_global("name", "world");
+//! interpreter.eval_str("const const greeting = `Hello, ${name}`!").unwrap();
+//! let greeting = interpreter.get_global("greeting").unwrap();
+//! assert_eq!(greeting.clone_inner(), Value::from("Hello, world"));
+//! ```
+
+use std::{fs, path::Path, rc::Rc};
+
+use types::prelude::{rc_mut_new, RcMut, State};
+
+mod builtins;
+mod interpreter;
+mod lexer;
+mod parser;
+#[cfg(test)]
+mod tests;
+mod types;
+
+pub use types::{
+    Boolean, Clock, Diagnostic, Error, Function, Imports, Keyword, Limit, Limits, NativeFunction,
+    Pointer, SResult, Span, StringSegment, Token, Value, VarType,
+};
+
+/// A DreamBerd interpreter, holding the global state that every evaluation runs in
+///
+/// Clones share the same state, which is torn down once the last one is dropped. Functions you got out of it won't
+/// have their variables anymore.
+#[derive(Debug, Clone)]
+pub struct Interpreter {
+    state: RcMut<State>,
+    _owner: Rc<Owner>,
+}
+
+/// Tears down the state when it's dropped, since functions and the states they close over keep each other alive
+#[derive(Debug)]
+struct Owner(RcMut<State>);
+
+impl Drop for Owner {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.0.try_borrow_mut() {
+            state.tear_down();
+        }
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let state = rc_mut_new(State::new());
+        Self {
+            _owner: Rc::new(Owner(state.clone())),
+            state,
+        }
+    }
+
+    /// Make an interpreter that stops programs which go over `limits`, for running code you don't trust
+    ///
+    /// ```
+    /// use dreamberd_rs::{Error, Interpreter, Limit, Limits};
+    ///
+    /// let interpreter = Interpreter::with_limits(Limits {
+    ///     max_steps: Some(1000),
+    ///     ..Limits::default()
+    /// });
+    /// let err = interpreter.eval_str("while(true, {})!").unwrap_err();
+    /// assert_eq!(err.error, Error::LimitExceeded(Limit::Steps, 1000));
+    /// ```
+    pub fn with_limits(limits: Limits) -> Self {
+        let interpreter = Self::new();
+        interpreter.set_limits(limits);
+        interpreter
+    }
+
+    /// Change the limits for the next runs
+    pub fn set_limits(&self, limits: Limits) {
+        self.state.borrow().budget.borrow_mut().limits = limits;
+    }
+
+    /// Change which files `import("file")` can read. By default it can read any of them, so restrict it when running
+    /// code you don't trust.
+    ///
+    /// ```
+    /// use dreamberd_rs::{Error, Imports, Interpreter};
+    ///
+    /// let interpreter = Interpreter::new();
+    /// interpreter.set_imports(Imports::Disabled);
+    /// let err = interpreter.eval_str("import(`/etc/passwd`)!").unwrap_err();
+    /// assert!(matches!(err.error, Error::ImportNotAllowed(_)));
+    /// ```
+    pub fn set_imports(&self, imports: Imports) {
+        self.state
+            .borrow()
+            .modules
+            .borrow_mut()
+            .set_imports(imports);
+    }
+
+    /// Run DreamBerd source code in the global state, returning the value of the last statement.
+    /// Declarations stick around for later calls. Each call gets the full limits again.
+    ///
+    /// # Errors
+    /// Returns a `Diagnostic` pointing into `source` if it fails to lex, parse, or run
+    pub fn eval_str(&self, source: &str) -> SResult<Pointer> {
+        self.state.borrow().budget.borrow_mut().reset();
+        interpreter::run_source(source, &self.state)
+    }
+
+    /// Read a file and run it like `Interpreter::eval_source`
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file can't be read, otherwise the same errors as `Interpreter::eval_str`
+    pub fn eval_file<P: AsRef<Path>>(&self, path: P) -> SResult<Pointer> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|err| Error::Io(path.display().to_string().into(), err.kind()))?;
+        self.eval_source(&source, path)
+    }
+
+    /// Run the source of the file at `path` like `Interpreter::eval_str`. `import` and `export` paths are relative to it.
+    ///
+    /// # Errors
+    /// Returns the same errors as `Interpreter::eval_str`
+    pub fn eval_source<P: AsRef<Path>>(&self, source: &str, path: P) -> SResult<Pointer> {
+        let path = path.as_ref();
+        let modules = {
+            let mut state = self.state.borrow_mut();
+            state.path = path.into();
+            state.modules.clone()
+        };
+        modules.borrow_mut().start(path);
+        let result = self.eval_str(source);
+        modules.borrow_mut().finish(path, result.is_ok());
+        result
+    }
+
+    /// Get a variable from the global state, if it's been declared
+    pub fn get_global(&self, name: &str) -> Option<Pointer> {
+        self.state.borrow().lookup(name)
+    }
+
+    /// Declare (or overwrite) a variable in the global state
+    pub fn set_global<P: Into<Pointer>>(&self, name: &str, value: P) {
+        self.state.borrow_mut().insert(name.into(), value.into());
+    }
+
+    /// Declare a `const const` function written in Rust. It gets called with its arguments already evaluated.
+    pub fn register_native<F: Fn(&[Pointer]) -> SResult<Pointer> + 'static>(
+        &self,
+        name: &str,
+        func: F,
+    ) {
+        self.state.borrow_mut().register_native(name, func);
+    }
+
+    /// Get the clock that variable lifetimes are measured with. Replace it with `Clock::manual()` to control time.
+    pub fn clock(&self) -> RcMut<Clock> {
+        self.state.borrow().clock.clone()
+    }
+
+    /// Get this interpreter's `undefined`
+    pub fn undefined(&self) -> Pointer {
+        self.state.borrow().undefined.clone()
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
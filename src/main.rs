@@ -1,21 +1,9 @@
 #![warn(clippy::pedantic, clippy::nursery)]
 
-use std::{
-    error::Error,
-    fs,
-    path::{Path, PathBuf},
-};
+use std::{error::Error, fs, path::PathBuf, process::ExitCode};
 
 use clap::{Parser, Subcommand};
-use interpreter::inner_interpret;
-use types::{rc_mut_new, Pointer, RcMut, State, Syntax};
-
-mod interpreter;
-mod lexer;
-mod parser;
-#[cfg(test)]
-mod tests;
-mod types;
+use dreamberd_rs::{Imports, Interpreter, Limits};
 
 macro_rules! input {
     ($msg: expr) => {{
@@ -32,6 +20,21 @@ macro_rules! input {
 struct Args {
     #[command(subcommand)]
     sub_command: SubcommandArg,
+    /// how many function calls can be running at once
+    #[arg(long, global = true, default_value_t = 4096)]
+    max_depth: usize,
+    /// how many expressions can be evaluated in one run
+    #[arg(long, global = true)]
+    max_steps: Option<usize>,
+    /// how many objects and arrays can be made in one run
+    #[arg(long, global = true)]
+    max_objects: Option<usize>,
+    /// how deeply code can be nested, counting every expression inside of another one
+    #[arg(long, global = true, default_value_t = STACK_SIZE / 16384)]
+    max_nesting: usize,
+    /// only let `import` read files inside of this directory
+    #[arg(long, global = true)]
+    import_root: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -46,54 +49,62 @@ enum SubcommandArg {
     },
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+/// Recursion that isn't at the end of a function still goes through the Rust stack, so give it plenty of room. Each
+/// level of `--max-nesting` takes up to 16 KB of it.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+fn main() -> Result<ExitCode, Box<dyn Error>> {
     let args = Args::parse();
+    // the interpreter isn't `Send`, so it's made on the new thread
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(args).map_err(|err| err.to_string()))?
+        .join()
+        .expect("the interpreter thread panicked")
+        .map_err(Into::into)
+}
+
+fn run(args: Args) -> Result<ExitCode, Box<dyn Error>> {
+    let interpreter = Interpreter::with_limits(Limits {
+        max_depth: Some(args.max_depth),
+        max_steps: args.max_steps,
+        max_objects: args.max_objects,
+        max_nesting: Some(args.max_nesting),
+    });
+    if let Some(root) = args.import_root {
+        interpreter.set_imports(Imports::Within(root));
+    }
     match args.sub_command {
         SubcommandArg::Run { path } => {
-            let _result = interpreter::interpret(&file_to_syntax(&PathBuf::from(path))?)?;
-            // println!("{result:?}");
+            let source = fs::read_to_string(&path)?;
+            if let Err(err) = interpreter.eval_source(&source, &path) {
+                eprintln!("{}", err.render(&path, &source));
+                return Ok(ExitCode::FAILURE);
+            }
         }
         SubcommandArg::Repl { path } => {
-            let state = rc_mut_new(State::new());
             if let Some(path) = path {
-                let syn = file_to_syntax(&PathBuf::from(path))?;
-                let Syntax::Block(statements) = syn else { panic!() };
-                for statement in statements {
-                    inner_interpret(&statement, state.clone())?;
+                let source = fs::read_to_string(&path)?;
+                if let Err(err) = interpreter.eval_source(&source, &path) {
+                    eprintln!("{}", err.render(&path, &source));
+                    return Ok(ExitCode::FAILURE);
                 }
-                // println!("{result}");
-                // println!("{state:?}");
             }
             loop {
                 let input = input!(">>> ");
                 if input.is_empty() {
-                    return Ok(());
+                    return Ok(ExitCode::SUCCESS);
                 }
-                let result = run_input(&input, state.clone());
-                match result {
+                match interpreter.eval_str(&input) {
                     Ok(ptr) => {
-                        if ptr != state.borrow().undefined {
+                        if ptr != interpreter.undefined() {
                             println!("{ptr}");
                         }
                     }
-                    Err(err) => println!("Error: {err}"),
+                    Err(err) => println!("{}", err.render("<repl>", &input)),
                 }
             }
         }
     }
-    Ok(())
-}
-
-fn run_input(input: &str, context: RcMut<State>) -> Result<Pointer, Box<dyn Error>> {
-    Ok(inner_interpret(
-        &parser::parse(lexer::tokenize(&format!("{{{input}}}"))?)?,
-        context,
-    )?)
-}
-
-fn file_to_syntax(path: &Path) -> Result<Syntax, Box<dyn Error>> {
-    let file = format!("{{{}}}", fs::read_to_string(path)?);
-    let tokens = lexer::tokenize(&file)?;
-    // println!("{tokens:?}");
-    parser::parse(tokens).map_err(Into::into)
+    Ok(ExitCode::SUCCESS)
 }
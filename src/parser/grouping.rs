@@ -1,10 +1,12 @@
-use std::iter::Peekable;
-
 use crate::types::prelude::*;
 
-use super::inner_parse;
+use super::{consume_whitespace, get_index, get_tuple, inner_parse, Tokens};
+
+pub(super) fn parse_group(tokens: &mut Tokens) -> SResult<Syntax> {
+    tokens.nested(parse_operations)
+}
 
-pub(super) fn parse_group<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>) -> SResult<Syntax> {
+fn parse_operations(tokens: &mut Tokens) -> SResult<Syntax> {
     let mut groups_buf = Vec::new();
     let tail;
     loop {
@@ -17,11 +19,56 @@ pub(super) fn parse_group<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>) -
 }
 
 /// get the next operator, including handling unaries
-fn grab_op<T: Iterator<Item = Token>>(
-    tokens: &mut Peekable<T>,
-    groups_buf: &mut Vec<OpGroup>,
-) -> SResult<Option<Syntax>> {
-    let left = inner_parse(tokens)?;
+fn grab_op(tokens: &mut Tokens, groups_buf: &mut Vec<OpGroup>) -> SResult<Option<Syntax>> {
+    consume_whitespace(tokens);
+    let mut left = match tokens.peek() {
+        Some(Token::PlusPlus) => {
+            tokens.next();
+            Syntax::Increment(true, Box::new(grab_target(tokens)?))
+        }
+        Some(Token::TackTack) => {
+            tokens.next();
+            Syntax::Decrement(true, Box::new(grab_target(tokens)?))
+        }
+        Some(Token::Ident(_)) => match inner_parse(tokens)? {
+            // `a.b(c)` calls `a.b`, not `b`
+            Syntax::Call(callee, args, span) => {
+                Syntax::Call(Box::new(take_dots(groups_buf, *callee)?), args, span)
+            }
+            other => other,
+        },
+        _ => inner_parse(tokens)?,
+    };
+    // calls, indexing, and postfix `++` and `--` apply to the whole `a.b.c` before them
+    loop {
+        match tokens.peek() {
+            Some(Token::LParen) => {
+                tokens.next();
+                let start = tokens.span();
+                consume_whitespace(tokens);
+                let args = get_tuple(tokens, &Token::RParen)?;
+                let span = start.to(tokens.span());
+                left = Syntax::Call(Box::new(take_dots(groups_buf, left)?), args, span);
+            }
+            Some(Token::LSquare) => {
+                tokens.next();
+                let (idx, idx_span) = get_index(tokens)?;
+                left = take_dots(groups_buf, left)?;
+                left = Syntax::Index(Box::new(left), Box::new(idx), idx_span);
+            }
+            _ => break,
+        }
+    }
+    while let Some(tok @ (Token::PlusPlus | Token::TackTack)) = tokens.peek() {
+        let is_increment = *tok == Token::PlusPlus;
+        tokens.next();
+        left = take_dots(groups_buf, left)?;
+        left = if is_increment {
+            Syntax::Increment(false, Box::new(left))
+        } else {
+            Syntax::Decrement(false, Box::new(left))
+        };
+    }
     let mut spc = if let Some(&Token::Space(spc)) = tokens.peek() {
         tokens.next();
         spc
@@ -48,27 +95,59 @@ fn grab_op<T: Iterator<Item = Token>>(
         Some(Token::And) => Operation::And,
         Some(Token::Or) => Operation::Or,
         Some(Token::Arrow) => Operation::Arrow,
-        Some(Token::PlusPlus) => {
-            todo!()
-        }
-        Some(Token::TackTack) => {
-            todo!()
-        }
         _ => {
             return Ok(Some(left));
         }
     };
     tokens.next();
+    let span = tokens.span();
     if let Some(&Token::Space(spc_right)) = tokens.peek() {
-        spc += spc_right;
+        spc = spc.saturating_add(spc_right);
     }
-    groups_buf.push((left, op, spc));
+    groups_buf.push((left, op, span, spc));
     Ok(None)
 }
 
+/// merge the `a.b.` right before `left` into `a.b.left`, grouping left to right like `group` would
+fn take_dots(groups_buf: &mut Vec<OpGroup>, left: Syntax) -> SResult<Syntax> {
+    let mut chain = Vec::new();
+    while let Some(dot) = groups_buf.pop_if(|(_, op, _, spc)| *op == Operation::Dot && *spc == 0) {
+        chain.push(dot);
+    }
+    let mut chain = chain.into_iter().rev();
+    let Some((mut target, mut op, mut span, _)) = chain.next() else {
+        return Ok(left);
+    };
+    for (next, next_op, next_span, _) in chain {
+        target = make_operation(target, op, span, next)?;
+        (op, span) = (next_op, next_span);
+    }
+    make_operation(target, op, span, left)
+}
+
+/// get the target of a prefix `++` or `--`, including any `.` accesses and indexing
+fn grab_target(tokens: &mut Tokens) -> SResult<Syntax> {
+    let mut target = inner_parse(tokens)?;
+    loop {
+        match tokens.peek() {
+            Some(Token::Dot) => {
+                tokens.next();
+                let span = tokens.span();
+                target = make_operation(target, Operation::Dot, span, inner_parse(tokens)?)?;
+            }
+            Some(Token::LSquare) => {
+                tokens.next();
+                let (idx, span) = get_index(tokens)?;
+                target = Syntax::Index(Box::new(target), Box::new(idx), span);
+            }
+            _ => return Ok(target),
+        }
+    }
+}
+
 /// merge operators until only the tail remains
 fn group(mut src: Vec<OpGroup>, mut tail: Syntax) -> SResult<Syntax> {
-    while let Some(&(_, _, val)) = src.iter().min_by_key(|(_, _, u)| u) {
+    while let Some(&(_, _, _, val)) = src.iter().min_by_key(|(_, _, _, u)| u) {
         (src, tail) = inner_group(src, tail, val)?;
     }
     Ok(tail)
@@ -78,40 +157,30 @@ fn group(mut src: Vec<OpGroup>, mut tail: Syntax) -> SResult<Syntax> {
 fn inner_group(src: Vec<OpGroup>, mut tail: Syntax, val: u8) -> SResult<(Vec<OpGroup>, Syntax)> {
     let mut grouping_buf: Vec<OpGroup> = Vec::new();
     let mut src_iter = src.into_iter();
-    while let Some((left, op, spc)) = src_iter.next() {
+    while let Some((left, op, span, spc)) = src_iter.next() {
         // if the operators are further apart, push it to a later iteration
         if spc != val {
-            grouping_buf.push((left, op, spc));
+            grouping_buf.push((left, op, span, spc));
         // if there's a next item, turn `[l op sp, r op sp]` into `[(l op r) op sp]`
-        } else if let Some((right, op_2, spc_2)) = src_iter.next() {
-            grouping_buf.push((make_operation(left, op, right)?, op_2, spc_2));
+        } else if let Some((right, op_2, span_2, spc_2)) = src_iter.next() {
+            grouping_buf.push((make_operation(left, op, span, right)?, op_2, span_2, spc_2));
         // if there's no next item, turn `[l op sp] r` into `l op r`
         } else {
-            tail = make_operation(left, op, tail)?;
+            tail = make_operation(left, op, span, tail)?;
         }
     }
     Ok((grouping_buf, tail))
 }
 
 /// if `op` is `->`, try to make it into a function
-fn make_operation(left: Syntax, op: Operation, right: Syntax) -> SResult<Syntax> {
+fn make_operation(left: Syntax, op: Operation, span: Span, right: Syntax) -> SResult<Syntax> {
     if op == Operation::Arrow {
         // println!("{left:?} -> {right:?}");
-        let input = match left {
-            Syntax::Block(vals) => vals
-                .into_iter()
-                .map(|syn| match syn {
-                    Syntax::Ident(ident) => Ok(ident),
-                    other => Err(format!(
-                        "Function input can only be identifiers, not {other:?}"
-                    )),
-                })
-                .collect::<Result<Vec<_>, _>>()?,
-            Syntax::Ident(ident) => vec![ident],
-            _ => todo!(),
-        };
+        let input = left
+            .into_params()
+            .map_err(|err| Diagnostic::new(err).with_span(span))?;
         Ok(Syntax::Function(input, Box::new(right)))
     } else {
-        Ok(Syntax::Operation(Box::new(left), op, Box::new(right)))
+        Ok(Syntax::Operation(Box::new(left), op, Box::new(right), span))
     }
 }
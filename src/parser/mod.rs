@@ -1,34 +1,147 @@
-use std::iter::Peekable;
+use std::time::Duration;
 
 use crate::types::prelude::*;
 
 mod grouping;
 
-pub fn parse(tokens: Vec<Token>) -> SResult<Syntax> {
-    let mut tokens = tokens.into_iter().peekable();
-    let mut syntax = Vec::new();
-    while tokens.peek().is_some() {
-        syntax.push(grouping::parse_group(&mut tokens)?);
+/// A stream of tokens that remembers where the last one came from, and how deeply nested the parser is in it
+struct Tokens {
+    inner: std::vec::IntoIter<(Token, Span)>,
+    last: Span,
+    /// The byte offset of every newline in the source
+    newlines: Vec<usize>,
+    nesting: usize,
+    max_nesting: Option<usize>,
+}
+
+impl Tokens {
+    fn new(source: &str, tokens: Vec<(Token, Span)>, budget: &Budget) -> Self {
+        Self {
+            inner: tokens.into_iter(),
+            last: Span::default(),
+            newlines: source.match_indices('\n').map(|(idx, _)| idx).collect(),
+            nesting: budget.nesting(),
+            max_nesting: budget.limits.max_nesting,
+        }
+    }
+
+    /// Run `func` one level deeper, since everything that's parsed inside of something else goes further down the
+    /// stack
+    fn nested<T>(&mut self, func: impl FnOnce(&mut Self) -> SResult<T>) -> SResult<T> {
+        if let Some(max) = self.max_nesting.filter(|&max| self.nesting >= max) {
+            let span = self
+                .inner
+                .as_slice()
+                .first()
+                .map_or_else(|| self.end(), |&(_, span)| span);
+            return Err(Diagnostic::new(Error::LimitExceeded(Limit::Nesting, max)).with_span(span));
+        }
+        self.nesting += 1;
+        let result = func(self);
+        self.nesting -= 1;
+        result
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.peek_nth(0)
+    }
+
+    /// Look `n` tokens past the next one
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.inner.as_slice().get(n).map(|(tok, _)| tok)
+    }
+
+    /// Get the span of the most recently consumed token
+    const fn span(&self) -> Span {
+        self.last
+    }
+
+    /// Get the 1-indexed source line the next token starts on
+    fn line(&self) -> usize {
+        let start = self
+            .inner
+            .as_slice()
+            .first()
+            .map_or(self.last.end, |(_, span)| span.start);
+        self.newlines.partition_point(|&idx| idx < start) + 1
+    }
+
+    /// Get an empty span just after the most recently consumed token
+    const fn end(&self) -> Span {
+        Span::new(self.last.end, self.last.end)
+    }
+
+    /// Point `error` at the most recently consumed token
+    const fn error(&self, error: Error) -> Diagnostic {
+        Diagnostic::new(error).with_span(self.span())
+    }
+}
+
+impl Iterator for Tokens {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let (tok, span) = self.inner.next()?;
+        self.last = span;
+        Some(tok)
+    }
+}
+
+/// Parse a file from the tokens of `source`, going no deeper than `budget` has room for
+pub fn parse(source: &str, tokens: Vec<(Token, Span)>, budget: &Budget) -> SResult<Syntax> {
+    let mut tokens = Tokens::new(source, tokens, budget);
+    let syntax = parse_statements(&mut tokens)?;
+    if let Some(tok) = tokens.next() {
+        return Err(tokens.error(Error::UnexpectedToken(tok)));
     }
     Ok(optimize(Syntax::Block(syntax)))
 }
 
-fn inner_parse<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>) -> SResult<Syntax> {
+/// parse statements until the end of the current block
+fn parse_statements(tokens: &mut Tokens) -> SResult<Vec<Syntax>> {
+    let mut statements_buf = Vec::new();
+    while let Some(tok) = tokens.peek() {
+        match tok {
+            Token::RSquirrely => break,
+            Token::Space(_) => {
+                tokens.next();
+                continue;
+            }
+            _ => {}
+        }
+        let line = tokens.line();
+        let inner = grouping::parse_group(tokens)?;
+        statements_buf.push(Syntax::Line(line, Box::new(consume_bang(inner, tokens))));
+    }
+    Ok(statements_buf)
+}
+
+fn inner_parse(tokens: &mut Tokens) -> SResult<Syntax> {
+    tokens.nested(parse_value)
+}
+
+fn parse_value(tokens: &mut Tokens) -> SResult<Syntax> {
     match tokens.next() {
         Some(Token::String(str)) => Ok(Syntax::String(str)),
         Some(Token::Tack | Token::Semicolon) => Ok(Syntax::Negate(Box::new(inner_parse(tokens)?))),
         Some(Token::Ident(id)) => {
+            let id_span = tokens.span();
             consume_whitespace(tokens);
             if id.as_ref() == "const" || id.as_ref() == "var" {
-                declare(tokens, &id)
+                declare(tokens, &id, id_span)
+            } else if let ("export" | "import", Some(Token::Ident(_))) =
+                (id.as_ref(), tokens.peek())
+            {
+                module_statement(tokens, &id, id_span)
             } else {
                 match tokens.peek() {
                     // call as a function
                     Some(Token::LParen) => {
                         tokens.next();
                         consume_whitespace(tokens);
-                        let input = get_tuple(tokens)?;
-                        Ok(consume_bang(Syntax::Call(id, input), tokens))
+                        let input = get_tuple(tokens, &Token::RParen)?;
+                        let span = id_span.to(tokens.span());
+                        Ok(Syntax::Call(Box::new(Syntax::Ident(id)), input, span))
                     }
                     Some(Token::Colon) => {
                         tokens.next();
@@ -41,47 +154,40 @@ fn inner_parse<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>) -> SResult<S
                 }
             }
         }
+        Some(Token::LSquirrely) if is_object_literal(tokens) => get_object(tokens),
         Some(Token::LSquirrely) => {
-            let mut statements_buf = Vec::new();
-            while let Some(tok) = tokens.peek() {
-                match tok {
-                    Token::RSquirrely => break,
-                    Token::Space(_) => {
-                        tokens.next();
-                        continue;
-                    }
-                    _ => {}
-                }
-                let inner = grouping::parse_group::<T>(tokens)?;
-                statements_buf.push(consume_bang(inner, tokens));
-            }
-            if tokens.next() == Some(Token::RSquirrely) {
-                Ok(Syntax::Block(statements_buf))
-            } else {
-                Err(String::from("Expected `}`"))
+            let start = tokens.span();
+            let statements_buf = parse_statements(tokens)?;
+            match tokens.next() {
+                Some(Token::RSquirrely) => Ok(Syntax::Block(statements_buf)),
+                other => Err(
+                    Diagnostic::new(Error::Expected("`}` to close this block", other))
+                        .with_span(start),
+                ),
             }
         }
         Some(Token::Space(_)) => inner_parse(tokens),
+        Some(Token::LSquare) => Ok(Syntax::Array(get_tuple(tokens, &Token::RSquare)?)),
         Some(Token::LParen) => {
-            let val = get_tuple(tokens)?;
+            let val = get_tuple(tokens, &Token::RParen)?;
             if let [x] = &val[..] {
                 Ok(x.clone())
             } else {
                 Ok(Syntax::Block(val))
             }
         }
-        Some(other) => Err(format!("Unexpected token `{other:?}`")),
-        None => Err(String::from("Unexpected End of File")),
+        Some(other) => Err(tokens.error(Error::UnexpectedToken(other))),
+        None => Err(Diagnostic::new(Error::UnexpectedEof).with_span(tokens.end())),
     }
 }
 
-fn consume_whitespace<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>) {
+fn consume_whitespace(tokens: &mut Tokens) {
     while let Some(Token::Space(_)) = tokens.peek() {
         tokens.next();
     }
 }
 
-fn consume_bang<T: Iterator<Item = Token>>(syn: Syntax, tokens: &mut Peekable<T>) -> Syntax {
+fn consume_bang(syn: Syntax, tokens: &mut Tokens) -> Syntax {
     match tokens.peek() {
         Some(&Token::Bang(q)) => {
             tokens.next();
@@ -95,27 +201,58 @@ fn consume_bang<T: Iterator<Item = Token>>(syn: Syntax, tokens: &mut Peekable<T>
     }
 }
 
-fn declare<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>, id: &str) -> SResult<Syntax> {
-    let Some(Token::Ident(second)) = tokens.next() else {
-                    return Err(String::from("Expected `const` or `var` after `{id}`"))
-                };
+fn declare(tokens: &mut Tokens, id: &str, start: Span) -> SResult<Syntax> {
+    let second = match tokens.next() {
+        Some(Token::Ident(second)) => second,
+        other => return Err(tokens.error(Error::Expected("`const` or `var`", other))),
+    };
     let var_type = match (id, second.as_ref()) {
         ("var", "var") => VarType::VarVar,
         ("var", "const") => VarType::VarConst,
         ("const", "var") => VarType::ConstVar,
         ("const", "const") => VarType::ConstConst,
         ("var" | "const", _) => {
-            return Err(format!(
-                "Expected `const` or `var` after `{id}`, not `{second}`"
-            ))
+            return Err(tokens.error(Error::Expected(
+                "`const` or `var`",
+                Some(Token::Ident(second)),
+            )))
         }
         _ => unreachable!(),
     };
     consume_whitespace(tokens);
-    let Some(Token::Ident(varname)) = tokens.next() else {
-                    return Err(format!("Expected a variable name after `{id} {second}`"))
-                };
+    // `const const const name`, but not `const const const = ...`, which declares `const`
+    let var_type = match (
+        var_type,
+        tokens.peek(),
+        tokens.peek_nth(1),
+        tokens.peek_nth(2),
+    ) {
+        (
+            VarType::ConstConst,
+            Some(Token::Ident(third)),
+            Some(Token::Space(_)),
+            Some(Token::Ident(_)),
+        ) if third.as_ref() == "const" => {
+            tokens.next();
+            consume_whitespace(tokens);
+            VarType::ConstConstConst
+        }
+        (var_type, ..) => var_type,
+    };
+    let varname = match tokens.next() {
+        Some(Token::Ident(varname)) => varname,
+        other => return Err(tokens.error(Error::Expected("a variable name", other))),
+    };
+    let span = start.to(tokens.span());
     consume_whitespace(tokens);
+    let lifetime = if tokens.peek() == Some(&Token::LCaret) {
+        tokens.next();
+        let lifetime = get_lifetime(tokens)?;
+        consume_whitespace(tokens);
+        Some(lifetime)
+    } else {
+        None
+    };
     // consume a type definition
     if tokens.peek() == Some(&Token::Colon) {
         tokens.next();
@@ -127,21 +264,90 @@ fn declare<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>, id: &str) -> SRe
         Some(Token::Bang(_)) => Syntax::Ident(String::new().into()),
         Some(Token::Equal(1)) => {
             consume_whitespace(tokens);
-            grouping::parse_group::<T>(tokens)?
+            grouping::parse_group(tokens)?
         }
         other => {
-            return Err(format!(
-                "Expected `!`, `:`, or `=` after variable name, got `{other:?}`"
-            ))
+            return Err(tokens.error(Error::Expected(
+                "`!`, `:`, or `=` after variable name",
+                other,
+            )))
         }
     };
     Ok(consume_bang(
-        Syntax::Declare(var_type, varname, Box::new(value)),
+        Syntax::Declare(var_type, varname, lifetime, Box::new(value), span),
         tokens,
     ))
 }
 
-fn get_tuple<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>) -> SResult<Vec<Syntax>> {
+/// parse `export name to "file.db"` or `import name`
+fn module_statement(tokens: &mut Tokens, id: &str, start: Span) -> SResult<Syntax> {
+    let Some(Token::Ident(name)) = tokens.next() else {
+        unreachable!("only called before an identifier")
+    };
+    let syntax = if id == "import" {
+        Syntax::Import(name, start.to(tokens.span()))
+    } else {
+        consume_whitespace(tokens);
+        match tokens.next() {
+            Some(Token::Ident(to)) if to.as_ref() == "to" => {}
+            other => {
+                return Err(tokens.error(Error::Expected("`to` after the exported name", other)))
+            }
+        }
+        consume_whitespace(tokens);
+        Syntax::Export(name, Box::new(inner_parse(tokens)?))
+    };
+    Ok(consume_bang(syntax, tokens))
+}
+
+/// check if the `{` that was just consumed starts an object literal, like `{key: value}`, instead of a block
+fn is_object_literal(tokens: &Tokens) -> bool {
+    let mut idx = usize::from(matches!(tokens.peek(), Some(Token::Space(_))));
+    if !matches!(tokens.peek_nth(idx), Some(Token::Ident(_))) {
+        return false;
+    }
+    idx += 1;
+    if matches!(tokens.peek_nth(idx), Some(Token::Space(_))) {
+        idx += 1;
+    }
+    tokens.peek_nth(idx) == Some(&Token::Colon)
+}
+
+/// parse the fields of an object literal, after the `{`
+fn get_object(tokens: &mut Tokens) -> SResult<Syntax> {
+    let start = tokens.span();
+    let mut fields = Vec::new();
+    loop {
+        consume_whitespace(tokens);
+        let key = match tokens.next() {
+            Some(Token::RSquirrely) => break,
+            Some(Token::Ident(key)) => key,
+            other => return Err(tokens.error(Error::Expected("a field name", other))),
+        };
+        consume_whitespace(tokens);
+        match tokens.next() {
+            Some(Token::Colon) => {}
+            other => return Err(tokens.error(Error::Expected("`:` after the field name", other))),
+        }
+        fields.push((key, grouping::parse_group(tokens)?));
+        consume_whitespace(tokens);
+        match tokens.next() {
+            Some(Token::Comma) => {}
+            Some(Token::RSquirrely) => break,
+            other => {
+                return Err(Diagnostic::new(Error::Expected(
+                    "`,` or `}` to close this object",
+                    other,
+                ))
+                .with_span(start))
+            }
+        }
+    }
+    Ok(Syntax::Object(fields))
+}
+
+/// parse comma-separated items until `close`. Each one can end with a `!` or `?`, like `if(x, print(x)!)`.
+fn get_tuple(tokens: &mut Tokens, close: &Token) -> SResult<Vec<Syntax>> {
     let mut args_buf = Vec::new();
     while let Some(tok) = tokens.peek() {
         match tok {
@@ -149,28 +355,102 @@ fn get_tuple<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>) -> SResult<Vec
                 tokens.next();
                 consume_whitespace(tokens);
             }
-            Token::RParen => {
+            tok if tok == close => {
                 tokens.next();
-                break;
+                return Ok(args_buf);
+            }
+            _ => {
+                let item = grouping::parse_group(tokens)?;
+                args_buf.push(consume_bang(item, tokens));
+            }
+        }
+    }
+    let expected = if *close == Token::RSquare {
+        "`]`"
+    } else {
+        "`)`"
+    };
+    Err(Diagnostic::new(Error::Expected(expected, None)).with_span(tokens.end()))
+}
+
+/// parse the inside of `[idx]`, after the `[`
+fn get_index(tokens: &mut Tokens) -> SResult<(Syntax, Span)> {
+    let start = tokens.span();
+    let mut idx = get_tuple(tokens, &Token::RSquare)?;
+    let span = start.to(tokens.span());
+    match (idx.pop(), idx.is_empty()) {
+        (Some(idx), true) => Ok((idx, span)),
+        (Some(_), false) => {
+            Err(Diagnostic::new(Error::Expected("one index", Some(Token::Comma))).with_span(span))
+        }
+        (None, _) => {
+            Err(Diagnostic::new(Error::Expected("an index", Some(Token::RSquare))).with_span(span))
+        }
+    }
+}
+
+/// parse the rest of a lifetime after the `<`: `2>`, `-1>`, `20s>`, or `Infinity>`
+fn get_lifetime(tokens: &mut Tokens) -> SResult<Lifetime> {
+    const EXPECTED: &str = "a lifetime like `<2>`, `<20s>`, or `<Infinity>`";
+    consume_whitespace(tokens);
+    if tokens.peek() == Some(&Token::Tack) {
+        tokens.next();
+        let lifetime = match tokens.next() {
+            Some(Token::Ident(id)) => match id.parse::<i64>() {
+                Ok(lines) => Lifetime::Lines(-lines),
+                Err(_) => {
+                    return Err(
+                        tokens.error(Error::Expected("a number of lines", Some(Token::Ident(id))))
+                    )
+                }
+            },
+            other => return Err(tokens.error(Error::Expected("a number of lines", other))),
+        };
+        return close_lifetime(tokens, lifetime);
+    }
+    let lifetime = match tokens.next() {
+        Some(Token::Ident(id)) if matches!(id.as_ref(), "Infinity" | "infinity" | "∞") => {
+            Lifetime::Infinity
+        }
+        Some(Token::Ident(id)) => {
+            if let Ok(lines) = id.parse() {
+                Lifetime::Lines(lines)
+            } else if let Some(secs) = id.strip_suffix('s').and_then(|secs| secs.parse().ok()) {
+                Lifetime::Time(Duration::from_secs(secs))
+            } else {
+                return Err(tokens.error(Error::Expected(EXPECTED, Some(Token::Ident(id)))));
             }
-            _ => args_buf.push(grouping::parse_group::<T>(tokens)?),
         }
+        other => return Err(tokens.error(Error::Expected(EXPECTED, other))),
+    };
+    close_lifetime(tokens, lifetime)
+}
+
+fn close_lifetime(tokens: &mut Tokens, lifetime: Lifetime) -> SResult<Lifetime> {
+    consume_whitespace(tokens);
+    match tokens.next() {
+        Some(Token::RCaret) => Ok(lifetime),
+        other => Err(tokens.error(Error::Expected("`>` after the lifetime", other))),
     }
-    Ok(args_buf)
 }
 
-fn get_type<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>) -> SResult<()> {
+fn get_type(tokens: &mut Tokens) -> SResult<()> {
+    tokens.nested(get_inner_type)
+}
+
+fn get_inner_type(tokens: &mut Tokens) -> SResult<()> {
     match tokens.next() {
         Some(Token::Ident(_)) => {}
-        other => return Err(format!("Expected a type after `:`; got `{other:?}`")),
+        other => return Err(tokens.error(Error::Expected("a type after `:`", other))),
     }
     consume_whitespace(tokens);
     match tokens.peek() {
         Some(Token::LSquare) => {
             tokens.next();
-            let Some(Token::RSquare) = tokens.next() else {
-                return Err(String::from("Expected `]` after `[` in type definition"))
-            };
+            match tokens.next() {
+                Some(Token::RSquare) => {}
+                other => return Err(tokens.error(Error::Expected("`]` in type definition", other))),
+            }
         }
         Some(Token::LCaret) => {
             tokens.next();
@@ -180,9 +460,10 @@ fn get_type<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>) -> SResult<()>
                 get_type(tokens)?;
                 consume_whitespace(tokens);
             }
-            let Some(Token::RCaret) = tokens.next() else {
-                return Err(String::from("Missing `>` in type definition"))
-            };
+            match tokens.next() {
+                Some(Token::RCaret) => {}
+                other => return Err(tokens.error(Error::Expected("`>` in type definition", other))),
+            }
         }
         _ => {}
     }
@@ -191,13 +472,17 @@ fn get_type<T: Iterator<Item = Token>>(tokens: &mut Peekable<T>) -> SResult<()>
 
 fn optimize(syn: Syntax) -> Syntax {
     match syn {
-        Syntax::Declare(typ, ident, inner) => {
-            Syntax::Declare(typ, ident, Box::new(optimize(*inner)))
+        Syntax::Declare(typ, ident, lifetime, inner, span) => {
+            Syntax::Declare(typ, ident, lifetime, Box::new(optimize(*inner)), span)
         }
         Syntax::Function(args, inner) => Syntax::Function(args, Box::new(optimize(*inner))),
-        Syntax::Call(ident, args) => Syntax::Call(ident, args.into_iter().map(optimize).collect()),
-        Syntax::Operation(lhs, op, rhs) => {
-            Syntax::Operation(Box::new(optimize(*lhs)), op, Box::new(optimize(*rhs)))
+        Syntax::Call(callee, args, span) => Syntax::Call(
+            Box::new(optimize(*callee)),
+            args.into_iter().map(optimize).collect(),
+            span,
+        ),
+        Syntax::Operation(lhs, op, rhs, span) => {
+            Syntax::Operation(Box::new(optimize(*lhs)), op, Box::new(optimize(*rhs)), span)
         }
         Syntax::Block(inner) => {
             let mut new_inner: Vec<_> = Vec::with_capacity(inner.len());
@@ -205,6 +490,10 @@ fn optimize(syn: Syntax) -> Syntax {
             for item in inner {
                 match item {
                     Syntax::Block(block) => new_inner.extend(block),
+                    Syntax::Line(line, statement) => match *statement {
+                        Syntax::Block(block) => new_inner.extend(block),
+                        other => new_inner.push(Syntax::Line(line, Box::new(other))),
+                    },
                     other => new_inner.push(other),
                 }
             }
@@ -218,7 +507,25 @@ fn optimize(syn: Syntax) -> Syntax {
         Syntax::Statement(is_debug, inner, lvl) => {
             Syntax::Statement(is_debug, Box::new(optimize(*inner)), lvl)
         }
+        Syntax::Line(line, inner) => Syntax::Line(line, Box::new(optimize(*inner))),
         Syntax::Negate(inner) => Syntax::Negate(Box::new(optimize(*inner))),
-        basic @ (Syntax::Ident(_) | Syntax::String(_)) => basic,
+        Syntax::Export(name, target) => Syntax::Export(name, Box::new(optimize(*target))),
+        Syntax::Array(items) => Syntax::Array(items.into_iter().map(optimize).collect()),
+        Syntax::Object(fields) => Syntax::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, optimize(value)))
+                .collect(),
+        ),
+        Syntax::Index(target, idx, span) => {
+            Syntax::Index(Box::new(optimize(*target)), Box::new(optimize(*idx)), span)
+        }
+        Syntax::Increment(is_prefix, inner) => {
+            Syntax::Increment(is_prefix, Box::new(optimize(*inner)))
+        }
+        Syntax::Decrement(is_prefix, inner) => {
+            Syntax::Decrement(is_prefix, Box::new(optimize(*inner)))
+        }
+        basic @ (Syntax::Ident(_) | Syntax::String(_) | Syntax::Import(..)) => basic,
     }
 }
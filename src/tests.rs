@@ -1,22 +1,24 @@
 use crate::types::prelude::*;
 
-use std::{f64::consts as f64, fmt::Display};
+use std::{f64::consts as f64, fmt::Display, rc::Rc};
 
 fn eval<T: Display>(src: T) -> SResult<Value> {
-    Ok(
-        crate::interpreter::interpret(&crate::parser::parse(crate::lexer::tokenize(&format!(
-            "{{{src}}}"
-        ))?)?)?
-        .clone_inner(),
-    )
+    let src = format!("{{{src}}}");
+    Ok(crate::interpreter::interpret(&crate::parser::parse(
+        &src,
+        crate::lexer::tokenize(&src)?,
+        &Budget::default(),
+    )?)?
+    .clone_inner())
 }
 
 macro_rules! assert_eq_db {
     ($lhs: expr, $rhs: expr) => {
-        let ltoks = crate::lexer::tokenize(&format!("{{{}}}", $lhs)).unwrap();
-        let rtoks = crate::lexer::tokenize(&format!("{{{}}}", $rhs)).unwrap();
-        let lsyn = crate::parser::parse(ltoks).unwrap();
-        let rsyn = crate::parser::parse(rtoks).unwrap();
+        let (lsrc, rsrc) = (format!("{{{}}}", $lhs), format!("{{{}}}", $rhs));
+        let ltoks = crate::lexer::tokenize(&lsrc).unwrap();
+        let rtoks = crate::lexer::tokenize(&rsrc).unwrap();
+        let lsyn = crate::parser::parse(&lsrc, ltoks, &Budget::default()).unwrap();
+        let rsyn = crate::parser::parse(&rsrc, rtoks, &Budget::default()).unwrap();
         let lres = crate::interpreter::interpret(&lsyn).unwrap().clone_inner();
         let rres = crate::interpreter::interpret(&rsyn).unwrap().clone_inner();
         assert_eq!(lres, rres, "{lsyn:?} != {rsyn:?}")
@@ -91,10 +93,11 @@ fn op_assign() {
 
 #[test]
 fn function() {
-    assert_eq!(
-        eval("const const does_she_really_like_you = () -> maybe! does_she_really_like_you"),
-        Ok(Value::Function(Vec::new(), Syntax::Ident("maybe".into())))
-    );
+    let func = eval("const const does_she_really_like_you = () -> maybe! does_she_really_like_you");
+    assert!(matches!(
+        func,
+        Ok(Value::Function(func)) if func.params().is_empty() && func.body() == &Syntax::Ident("maybe".into())
+    ));
 }
 
 #[test]
@@ -155,5 +158,1132 @@ fn eval_tests() {
     assert_eq_db!("eval(2)", "2");
     assert_eq_db!("const var x = 1! x += 2! eval(`x`)", "3");
     assert_eq_db!("const var x = 1! x += 2! eval(x)", "3");
-    assert_eq_db!("const const x = `'Hello, World!'`! eval(x)", "`Hello, World!`");
+    assert_eq_db!(
+        "const const x = `'Hello, World!'`! eval(x)",
+        "`Hello, World!`"
+    );
+}
+
+#[test]
+fn spans() {
+    let tokens = crate::lexer::tokenize("const const 👍 = true!").unwrap();
+    assert_eq!(tokens[4], (Token::Ident("👍".into()), Span::new(12, 16)));
+    assert_eq!(tokens[9], (Token::Bang(1), Span::new(23, 24)));
+
+    let src = "const const name = `Ava`!\n\tname = `John`!";
+    let err = crate::interpreter::interpret(
+        &crate::parser::parse(
+            src,
+            crate::lexer::tokenize(src).unwrap(),
+            &Budget::default(),
+        )
+        .unwrap(),
+    )
+    .unwrap_err();
+    assert_eq!(err.span, Some(Span::new(32, 33)));
+    assert_eq!(
+        err.render("main.db", src),
+        format!(
+            "error: {}\n --> main.db:2:7\n  |\n2 | \tname = `John`!\n  | \t     ^",
+            err.error
+        )
+    );
+
+    // parsed without the `{}` that `eval` adds, so nothing else closes them
+    for (src, expected) in [("print(1", "`)`"), ("if(true, 1", "`)`"), ("[1, 2", "`]`")] {
+        let err = crate::parser::parse(
+            src,
+            crate::lexer::tokenize(src).unwrap(),
+            &Budget::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.error, Error::Expected(expected, None));
+        assert_eq!(err.span, Some(Span::new(src.len(), src.len())));
+    }
+}
+
+#[test]
+fn error_sources() {
+    use std::error::Error as _;
+    let err = eval("const const x = 1! x = 2!").unwrap_err();
+    assert!(err.source().is_none());
+    let err = eval("eval(`)`)!").unwrap_err();
+    assert_eq!(
+        err.source().map(ToString::to_string),
+        Some(Error::UnexpectedToken(Token::RParen).to_string())
+    );
+}
+
+#[test]
+fn eval_spans() {
+    let src = "const const x = 1!\neval(`x(`)!";
+    let err = crate::interpreter::interpret(
+        &crate::parser::parse(
+            src,
+            crate::lexer::tokenize(src).unwrap(),
+            &Budget::default(),
+        )
+        .unwrap(),
+    )
+    .unwrap_err();
+    assert_eq!(err.span, Some(Span::new(19, 29)));
+    // thrown values pass through `eval`, but still point at it
+    let src = "const const x = 1!\neval(`throw(5)`)!";
+    let err = crate::interpreter::interpret(
+        &crate::parser::parse(
+            src,
+            crate::lexer::tokenize(src).unwrap(),
+            &Budget::default(),
+        )
+        .unwrap(),
+    )
+    .unwrap_err();
+    assert!(matches!(err.error, Error::Thrown(_)));
+    assert_eq!(err.span, Some(Span::new(19, 35)));
+}
+
+#[test]
+fn error_kinds() {
+    let err = eval("const const name = `Ava`! name = `John`!").unwrap_err();
+    assert!(matches!(
+        err.error,
+        Error::AssignToConst(Pointer::ConstConst(_))
+    ));
+    let err = eval("const var name = `Ava`! name = `John`!").unwrap_err();
+    assert!(matches!(
+        err.error,
+        Error::AssignToConst(Pointer::ConstVar(_))
+    ));
+    let err = eval("const const x = 1! x(2)!").unwrap_err();
+    assert!(matches!(err.error, Error::NotAFunction(_)));
+    let err = eval("if(true)!").unwrap_err();
+    assert!(matches!(err.error, Error::InvalidArguments(Keyword::If, _)));
+    let err = eval("eval(`)`)!").unwrap_err();
+    assert!(
+        matches!(err.error, Error::Eval(inner) if inner.error == Error::UnexpectedToken(Token::RParen))
+    );
+    let err = eval("const wat x = 1!").unwrap_err();
+    assert_eq!(
+        err.error,
+        Error::Expected("`const` or `var`", Some(Token::Ident("wat".into())))
+    );
+}
+
+#[test]
+fn comments() {
+    assert_eq_db!(";'hello there' // 'ereht olleh'\n", "'ereht olleh'");
+    assert_eq_db!("const var age = 1!\nage += 1! // 2\nage", "2");
+    assert_eq_db!("1 /* plus */ + /* two */ 2*3", "7");
+    assert_eq_db!("/* the answer\n   is */ 6 / 2", "3");
+    assert_eq_db!("6 / 2 // divided\n", "3");
+    assert_eq!(
+        crate::lexer::tokenize("1 /* never closed")
+            .unwrap_err()
+            .error,
+        Error::Expected("`*/` to close this comment", None)
+    );
+}
+
+#[test]
+fn increment_decrement() {
+    assert_eq_db!("var var count = 0! count++! count", "1");
+    assert_eq_db!("const var count = 5! count--! count--! count", "3");
+    assert_eq_db!("var var count = 0! count++", "0");
+    assert_eq_db!("var var count = 0! ++count", "1");
+    assert_eq_db!("var var count = 3! --count", "2");
+    assert_eq_db!("var var count = 3! count-- + 10", "13");
+    assert_eq_db!("var var count = 1! count++ * 2! count", "2");
+    // value-const pointers can't be edited, just like with `+=`
+    assert_eq_db!("const const count = 1! count++! ++count! count", "1");
+    assert_eq_db!("var const count = 1! count--! count", "1");
+    assert_eq_db!(
+        "const var o = {}! o.count = 1! o.count++! ++o.count! o.count",
+        "3"
+    );
+}
+
+#[test]
+fn no_panics() {
+    // comparing and sorting values of any type
+    assert_eq_db!("{} < {}", "false");
+    assert_eq_db!("(() -> 1) < (() -> 2)", "true");
+    assert_eq_db!("(() -> 1) >= (() -> 1)", "true");
+    let scope = rc_mut_new(State::new());
+    let function = |body: &str| {
+        Value::Function(Function::new(
+            Vec::new(),
+            Syntax::Ident(body.into()),
+            scope.clone(),
+        ))
+    };
+    let mut values = [
+        function("b"),
+        Value::empty_object(),
+        Value::Number(f64::NAN),
+        function("a"),
+        Value::from(1.0),
+    ];
+    values.sort();
+    assert_eq!(values[0], Value::from(1.0));
+    assert_eq!(values[3], function("a"));
+    // containers that hold themselves
+    assert_eq_db!("const var o = {}! o.me = o! o < o", "false");
+    assert_eq_db!("const var o = {}! o.me = o! o <= o", "true");
+    assert_eq_db!("const var o = {}! o.me = o! o == o", "true");
+    assert_eq_db!("const var o = {}! o.me = o! o === o", "true");
+    assert_eq_db!(
+        "const var o = {}! o.me = o! `${o}`",
+        "`object { me: [circular] }`"
+    );
+    assert_eq_db!(
+        "const var a = {}! a.me = a! const var b = {}! b.me = b! a >= b",
+        "true"
+    );
+    assert_eq_db!("const var a = [1]! a[0] = a! a < a", "false");
+    assert_eq_db!("const var a = [1]! a[0] = a! `${a}`", "`[1, [circular]]`");
+    // objects can be used as keys
+    assert_eq_db!(
+        "const var o = {}! const var k = {}! k.x = 1! o.k = 2! o.k",
+        "2"
+    );
+
+    // `->` with something other than identifiers
+    assert!(matches!(
+        eval("`a` -> 1").unwrap_err().error,
+        Error::InvalidParameter(param) if &*param == "\"a\""
+    ));
+    let arrow = Syntax::Operation(
+        Box::new(Syntax::Ident("x".into())),
+        Operation::Arrow,
+        Box::new(Syntax::Ident("x".into())),
+        Span::default(),
+    );
+    assert!(matches!(
+        crate::interpreter::interpret(&arrow).unwrap().clone_inner(),
+        Value::Function(func) if func.params() == [Rc::from("x")] && func.body() == &Syntax::Ident("x".into())
+    ));
+
+    // `self` can be modified while calling it
+    assert_eq_db!(
+        "const var o = {}! o.count = 0! o.call = ()->{self.count += 1}! o()! o()! o.count",
+        "2"
+    );
+    assert_eq_db!("var var f = ()->{f = 2}! f()! f", "2");
+
+    assert_eq_db!("`é🥧` * 1.5", "`é🥧é`");
+    assert_eq_db!("`a` * ∞", "undefined");
+    assert_eq_db!("`ab` * 1e30", "undefined");
+    assert_eq_db!("`ab` * ;1e30", "undefined");
+    assert_eq_db!("`ab` * 1.5", "`aba`");
+    // `<` and friends compare numbers like floats, even though `NaN` sorts last
+    assert_eq_db!(";0 < 0", "false");
+    assert_eq_db!("0 <= ;0", "true");
+    assert_eq_db!("NaN > 1", "false");
+    assert_eq_db!("1 < NaN", "false");
+    assert_eq!(
+        Syntax::Call(
+            Box::new(Syntax::Ident("f".into())),
+            Vec::new(),
+            Span::default()
+        )
+        .to_string(),
+        "f()"
+    );
+    eval(format!("1{}+ 2", " ".repeat(300))).unwrap();
+    eval(format!("1{}", "!".repeat(300))).unwrap();
+}
+
+#[test]
+fn native_functions() {
+    let interpreter = crate::Interpreter::new();
+    interpreter.register_native("sum", |args| {
+        let mut total = 0.0;
+        for arg in args {
+            total += f64::try_from(arg.clone_inner())
+                .map_err(|val| Error::Native(format!("`{val}` isn't a number").into()))?;
+        }
+        Ok(Pointer::from(total))
+    });
+    assert_eq!(
+        interpreter.eval_str("sum(1, 2, 3)").unwrap(),
+        Value::from(6.0)
+    );
+    assert_eq!(
+        interpreter
+            .eval_str("const const s = sum! s(2*2, 1)")
+            .unwrap(),
+        Value::from(5.0)
+    );
+    // through the `call` protocol
+    assert_eq!(
+        interpreter
+            .eval_str("const var o = {}! o.call = sum! o(4, 5)")
+            .unwrap(),
+        Value::from(9.0)
+    );
+    assert_eq!(
+        interpreter.eval_str("sum(1, `two`)").unwrap_err().error,
+        Error::Native("`two` isn't a number".into())
+    );
+    assert_eq!(
+        interpreter.eval_str("sum ==== sum").unwrap(),
+        Value::from(true)
+    );
+}
+
+#[test]
+fn std_library() {
+    assert_eq_db!("print(`hi`)", "`hi`");
+    assert_eq_db!("str(1+2)", "`3`");
+    assert_eq_db!("identity(true)", "true");
+    assert_eq_db!("bool(0.5)", "maybe");
+    assert_eq_db!("bool(`a`)", "true");
+    assert_eq_db!("db()", "undefined");
+    assert_eq_db!("const const x = use(1)! x(2)! x()", "2");
+    assert_eq_db!("const const x = use(1)! x(2)", "1");
+    assert_eq_db!("const const x = use(1)! x(3)! x.value", "3");
+    // the built-ins can be shadowed like anything else
+    assert_eq_db!("const const str = 5! str", "5");
+}
+
+#[test]
+fn imports() {
+    let dir = std::env::temp_dir().join(format!("dreamberd-imports-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, source: &str| std::fs::write(dir.join(name), source).unwrap();
+    write(
+        "lib.db",
+        "const const add = (a, b)->{a + b}!\nexport add to \"main.db\"!\nprint(`loaded`)!",
+    );
+    write("a.db", "import(\"b.db\")!");
+    write("b.db", "import(\"a.db\")!");
+    write("main.db", "import(\"lib.db\")!\nimport add!\nadd(2, 5)");
+    write("missing.db", "import nothing!");
+    write(
+        "bad.db",
+        "const const x = 1!\nexport x to \"main.db\"!\nthrow(`oops`)!",
+    );
+
+    let interpreter = crate::Interpreter::new();
+    assert_eq!(
+        interpreter.eval_file(dir.join("main.db")).unwrap(),
+        Value::from(7.0)
+    );
+    // files only run once, and `import` gives back everything exported to this file
+    let exports = interpreter.eval_str("import(`lib.db`)").unwrap();
+    let Value::Object(exports) = exports.clone_inner() else {
+        panic!("expected an object, got {exports:?}");
+    };
+    assert_eq!(exports.keys().collect::<Vec<_>>(), [&Value::from("add")]);
+
+    let err = crate::Interpreter::new()
+        .eval_file(dir.join("a.db"))
+        .unwrap_err();
+    let Error::Import(_, inner) = err.error else {
+        panic!("expected an import error, got {err:?}");
+    };
+    assert!(matches!(inner.error, Error::ImportCycle(_)));
+    // an error in a function from another file points at the call, since that's the file being run
+    write(
+        "fail.db",
+        "const const fail = ()->{\n\n\nthrow(1)!\n}!\nexport fail to \"t.db\"!",
+    );
+    let src = "import(\"fail.db\")!\nimport fail!\nfail()!";
+    let err = crate::Interpreter::new()
+        .eval_source(src, dir.join("t.db"))
+        .unwrap_err();
+    let start = src.find("fail()").unwrap();
+    assert_eq!(err.span, Some(Span::new(start, start + "fail()".len())));
+    // paths are the same however they're written
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    write("c.db", "import(\"sub/d.db\")!");
+    write("sub/d.db", "import(\"../c.db\")!");
+    let err = crate::Interpreter::new()
+        .eval_file(dir.join("c.db"))
+        .unwrap_err();
+    let Error::Import(_, inner) = err.error else {
+        panic!("expected an import error, got {err:?}");
+    };
+    assert!(matches!(inner.error, Error::ImportCycle(_)));
+    write(
+        "e.db",
+        "const const y = 3!\nexport y to \"sub/../main.db\"!",
+    );
+    assert_eq!(
+        crate::Interpreter::new()
+            .eval_source("import(`./e.db`)! import y! y", dir.join("main.db"))
+            .unwrap(),
+        Value::from(3.0)
+    );
+
+    let err = crate::Interpreter::new()
+        .eval_file(dir.join("missing.db"))
+        .unwrap_err();
+    assert_eq!(err.error, Error::NotExported("nothing".into()));
+    assert_eq!(err.span, Some(Span::new(0, 14)));
+
+    // a file that failed runs again, instead of giving back what it exported before failing
+    let err = crate::Interpreter::new()
+        .eval_source(
+            "try(import(`bad.db`), 0)! import(`bad.db`)",
+            dir.join("main.db"),
+        )
+        .unwrap_err();
+    assert!(matches!(err.error, Error::Import(..)));
+
+    // imports can be kept inside of a directory, or turned off
+    let root = dir.join("root");
+    std::fs::create_dir_all(&root).unwrap();
+    write("root/lib.db", "export 1 to \"main.db\"!");
+    write("secret.db", "secret");
+    let interpreter = crate::Interpreter::new();
+    interpreter.set_imports(crate::Imports::Within(root.clone()));
+    let import =
+        |file: &str| interpreter.eval_source(&format!("import(`{file}`)"), root.join("main.db"));
+    import("lib.db").unwrap();
+    let not_allowed =
+        |file: &str| matches!(import(file).unwrap_err().error, Error::ImportNotAllowed(_));
+    assert!(not_allowed("../secret.db"));
+    assert!(not_allowed("./../root/../secret.db"));
+    assert!(not_allowed(&dir.join("secret.db").display().to_string()));
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(dir.join("secret.db"), root.join("link.db")).unwrap();
+        assert!(not_allowed("link.db"));
+    }
+    interpreter.set_imports(crate::Imports::Disabled);
+    assert!(not_allowed("lib.db"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn file_separators() {
+    let src = "const const a = 1!\n=====\na\n======= b.db ==\nb!";
+    let files = crate::lexer::split_files(crate::lexer::tokenize(src).unwrap());
+    let names = files
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(names, [None, None, Some("b.db".into())]);
+    // spans still point into the whole source
+    assert_eq!(
+        files[2].1[0],
+        (
+            Token::Ident("b".into()),
+            Span::new(src.len() - 2, src.len() - 1)
+        )
+    );
+    // a named separator needs `=` on both sides, so this is still a comparison
+    assert_eq_db!("const const pi = 3!\npi\n===== pi", "true");
+
+    let interpreter = crate::Interpreter::new();
+    let src = "const const a = 1!\n=====\na\n";
+    assert_eq!(interpreter.eval_str(src).unwrap(), Value::from("a"));
+    // the first file runs in the global state
+    assert_eq!(interpreter.get_global("a").unwrap(), Value::from(1.0));
+    let src = "===== add.db ==\nconst const add = (a, b)->{a + b}!\nexport add to \"main.db\"!\n===== main.db ==\nimport add!\nadd(3, 2)";
+    assert_eq!(interpreter.eval_str(src).unwrap(), Value::from(5.0));
+    // every file is parsed before any of them run
+    assert!(interpreter
+        .eval_str("const const ran = 1!\n=====\nx = = 2!")
+        .is_err());
+    assert_eq!(interpreter.get_global("ran"), None);
+}
+
+#[test]
+fn arrays() {
+    assert_eq_db!("const const a = [3, 2, 5]! a[-1]", "3");
+    assert_eq_db!("const const a = [3, 2, 5]! a[1]", "5");
+    assert_eq_db!("const const a = [3, 2, 5]! a[2]", "undefined");
+    assert_eq_db!("const const a = [3, 2, 5]! a[0.5]", "undefined");
+    assert_eq_db!("[1, 2+1][0]", "3");
+    assert_eq_db!("const var a = [3, 2, 5]! a[0.5] = 4! a", "[3, 2, 4, 5]");
+    assert_eq_db!("const var a = [3, 2, 5]! a[-1.5] = 4! a", "[4, 3, 2, 5]");
+    assert_eq_db!("const var a = [3, 2, 5]! a[5] = 4! a", "[3, 2, 5, 4]");
+    assert_eq_db!("var var a = [3, 2, 5]! a[0] = 4! a[0]++! a", "[3, 5, 5]");
+    assert_eq_db!("const var o = {}! o.a = [1]! o.a[-1] = 2! o.a", "[2]");
+    // elements of a value-const array can't be edited
+    assert_eq_db!("const const a = [3, 2, 5]! a[0] += 1! a", "[3, 2, 5]");
+    assert_eq!(
+        eval("var const a = [3]! a[-1] = 1!").unwrap_err().error,
+        Error::AssignToConst(Pointer::VarConst(rc_mut_new(Rc::new(Value::Array(vec![
+            Pointer::from(3.0).convert(VarType::VarVar)
+        ])))))
+    );
+    assert_eq_db!("[1, 2] ==== [1, 2]", "true");
+    assert_eq_db!("bool([])", "false");
+}
+
+#[test]
+fn object_literals() {
+    assert_eq_db!("const const o = {a: 1, b: 2}! o.a + o.b", "3");
+    assert_eq_db!(
+        "const var o = {}! o.name = `Sam`! o.age = 2! o",
+        "{ name: `Sam`, age: 1+1, }"
+    );
+    assert_eq_db!("const var o = {n: 1}! o.n += 1! o.n", "2");
+    assert_eq_db!("const var o = {inner: {x: [1, 2]}}! o.inner.x[0]", "2");
+    // a block is still a block
+    assert_eq_db!("{ 1! 2 }", "2");
+    assert_eq!(
+        eval("{a: 1 b: 2}").unwrap_err().error,
+        Error::Expected(
+            "`,` or `}` to close this object",
+            Some(Token::Ident("b".into()))
+        )
+    );
+}
+
+#[test]
+fn bracket_indexing() {
+    assert_eq_db!("const const o = {a: 1}! o[`a`]", "1");
+    assert_eq_db!("const const o = {a: 1}! const const k = `a`! o[k] + 1", "2");
+    assert_eq_db!("const var o = {}! o[`a b`] = 2! o[`a b`]", "2");
+    assert_eq_db!("const var o = {}! o[1+1] = `two`! o[2]", "`two`");
+    // bracket and dot access share fields
+    assert_eq_db!("const var o = {}! o[`x`] = 2! o.x", "2");
+    assert_eq_db!("const var o = {}! o.x = 2! o[`x`] += 1! o.x", "3");
+    // missing fields are created as `undefined`, just like with `.`
+    assert_eq_db!("const var o = {}! o[`x`]! o", "{x: undefined}");
+    assert_eq_db!("const var o = {a: {b: [5]}}! o[`a`][`b`][-1]", "5");
+    // an object can be its own key, which is whatever it was when it was used
+    assert_eq_db!("const var o = {}! o[o] = 1! o[{}]", "1");
+    eval("const var o = {}! o[o]! o[o] = 2! o.me = o! o.me[o]! o.(o)").unwrap();
+    assert_eq_db!("`abc`[0]", "undefined");
+}
+
+#[test]
+fn method_calls() {
+    assert_eq_db!(
+        "const var c = {count: 0, add: n->{self.count += n! self.count}}! c.add(2)! c.add(3)",
+        "5"
+    );
+    assert_eq_db!(
+        "const var c = {count: 1}! c.get = ()->{self.count}! c.count = 4! c.get()",
+        "4"
+    );
+    // calls can be chained, and anything can be called
+    assert_eq_db!(
+        "const const o = {make: ()->{{double: x->{x*2}}}}! o.make().double(4)",
+        "8"
+    );
+    assert_eq_db!("const const f = x->{y->{y * 2}}! f(1)(2)", "4");
+    assert_eq_db!("const const fs = [x->{x*3}]! fs[-1](2)", "6");
+    // builtins work as methods too
+    assert_eq_db!("const const o = {show: str}! o.show(1)", "`1`");
+    assert_eq!(
+        eval("const const o = {}! o.missing(1)").unwrap_err().span,
+        Some(Span::new(23, 33))
+    );
+    // arguments can still end with `!`
+    assert_eq_db!("if(true, print(`hi`)!, 0)!", "undefined");
+    assert_eq_db!("const const f = x->{x}! if(true, f(1)!)!", "undefined");
+    assert_eq_db!("const const f = x->{x}! if(true, f(1)?, 0)", "1");
+}
+
+#[test]
+fn closures() {
+    // each counter keeps its own count
+    assert_eq_db!(
+        "const const make = ()->{var var n = 0! ()->{n += 1! n}}! const const a = make()! const const b = make()! a()! a()! b()! a()",
+        "3"
+    );
+    assert_eq_db!(
+        "const const add = x->{y->{x + y}}! const const add2 = add(2)! add2(3)",
+        "5"
+    );
+    assert_eq_db!("const const add = x->{y->{x + y}}! add(1)(2)", "3");
+    // the body sees where the function was made, not where it was called
+    assert_eq_db!(
+        "const const x = 1! const const get = ()->{x}! const const f = ()->{const const x = 2! get()}! f()",
+        "1"
+    );
+}
+
+#[test]
+fn loops() {
+    assert_eq_db!("var var i = 0! while(i < 5, i += 1)! i", "5");
+    assert_eq_db!(
+        "var var i = 0! while(true, {i += 1! if(i >= 3, break())})! i",
+        "3"
+    );
+    assert_eq_db!(
+        "var var i = 0! var var sum = 0! while(i < 6, {i += 1! if(i % 2 ==== 0, continue())! sum += i})! sum",
+        "9"
+    );
+    // `maybe` runs the third argument, or stops the loop if there isn't one
+    assert_eq_db!(
+        "var var n = 0! var var c = maybe! while(c, {}, {n += 1! if(n >= 3, c = false)})! n",
+        "3"
+    );
+    assert_eq_db!("var var n = 0! while(maybe, n += 1)! n", "0");
+    // `break` only stops the innermost loop
+    assert_eq_db!(
+        "var var i = 0! var var n = 0! while(i < 3, {i += 1! while(true, {n += 1! break()})})! n",
+        "3"
+    );
+    assert_eq!(eval("break()").unwrap_err().error, Error::Break);
+    // or a loop outside of the function it's in
+    assert_eq!(
+        eval("var var i = 0! const const f = ()->{break()}! while(true, {i += 1! f()!})!")
+            .unwrap_err()
+            .error,
+        Error::Misplaced(Keyword::Break)
+    );
+    assert_eq!(
+        eval("const const f = ()->{continue()}! while(true, f())!")
+            .unwrap_err()
+            .error,
+        Error::Misplaced(Keyword::Continue)
+    );
+    assert!(matches!(
+        eval("while(true)").unwrap_err().error,
+        Error::InvalidArguments(Keyword::While, _)
+    ));
+}
+
+#[test]
+fn early_return() {
+    let sign = "const const sign = n->{if(n < 0, return(;1))! if(n ==== 0, return(0))! 1}!";
+    assert_eq_db!(format!("{sign} sign(;5)"), ";1");
+    assert_eq_db!(format!("{sign} sign(0)"), "0");
+    assert_eq_db!(format!("{sign} sign(5)"), "1");
+    assert_eq_db!("const const f = ()->{return()! 1}! f()", "undefined");
+    // `return` leaves loops, but only the innermost function
+    assert_eq_db!(
+        "const const find = ()->{var var i = 0! while(true, {i += 1! if(i ==== 4, return(i))})! 0}! find()",
+        "4"
+    );
+    assert_eq_db!(
+        "const const inner = ()->{return(1)! 2}! const const outer = ()->{inner() + 10}! outer()",
+        "11"
+    );
+    assert_eq_db!("const const f = ()->{eval(`return(3)`)! 4}! f()", "3");
+    assert!(matches!(
+        eval("return(1)").unwrap_err().error,
+        Error::Return(_)
+    ));
+    assert!(matches!(
+        eval("const const f = ()->{return(1, 2)}! f()")
+            .unwrap_err()
+            .error,
+        Error::InvalidArguments(Keyword::Return, _)
+    ));
+}
+
+#[test]
+fn exceptions() {
+    assert_eq_db!("try(throw(5), e->{e + 1})", "6");
+    assert_eq_db!("try(1, e->{2})", "1");
+    assert_eq_db!("try(throw(1), 2)", "2");
+    assert_eq_db!("try(throw(3), str)", "`3`");
+    assert_eq_db!(
+        "const const f = ()->{throw(`bad`)! 1}! try(f(), e->{e})",
+        "`bad`"
+    );
+    assert_eq_db!("try(eval(`throw(4)`), e->{e})", "4");
+    // errors from the interpreter come through as their message
+    assert_eq!(
+        eval("const const x = 1! try(x = 2, e->{e})"),
+        Ok(Value::from("Can't assign to a `const const` ConstConst(1)"))
+    );
+    assert_eq_db!("try(eval(`1 +`), e->{`caught`})", "`caught`");
+    // `return` isn't an error
+    assert_eq_db!("const const f = ()->{try(return(1), 2)! 3}! f()", "1");
+    // errors in the handler aren't caught
+    assert_eq!(
+        eval("try(throw(1), e->{throw(e + 1)})").unwrap_err().error,
+        Error::Thrown(Pointer::from(2.0))
+    );
+    assert!(matches!(
+        eval("try(1)").unwrap_err().error,
+        Error::InvalidArguments(Keyword::Try, _)
+    ));
+}
+
+#[test]
+fn tail_calls() {
+    // calls at the end of a function don't use up the stack
+    assert_eq_db!(
+        "const const down = n->{if(n > 0, down(n - 1), `done`)}! down(10000)",
+        "`done`"
+    );
+    assert_eq_db!(
+        "var var total = 0! const const f = n->{total += 1! if(n > 0, f(n - 1))!}! f(10000)! total",
+        "10001"
+    );
+    assert_eq_db!(
+        "const const is_even = n->{if(n ==== 0, true, is_odd(n - 1))}! const const is_odd = n->{if(n ==== 0, false, is_even(n - 1))}! is_even(10001)",
+        "false"
+    );
+    // the result of a `!` statement is still thrown away
+    assert_eq_db!(
+        "const const one = ()->{1}! const const f = ()->{one()!}! f()",
+        "undefined"
+    );
+    assert_eq_db!(
+        "const const counter = {count: 0, add: n->{self.count += 1! if(n > 1, self.add(n - 1), self.count)}}! counter.add(10000)",
+        "10000"
+    );
+}
+
+/// Make something that runs source code with `limits`, in one interpreter
+fn limited(limits: Limits) -> impl Fn(&str) -> SResult<Value> {
+    let interpreter = crate::Interpreter::with_limits(limits);
+    move |src| interpreter.eval_str(src).map(|ptr| ptr.clone_inner())
+}
+
+#[test]
+fn limits() {
+    let depth = limited(Limits {
+        max_depth: Some(20),
+        ..Limits::default()
+    });
+    assert_eq!(
+        depth("const const f = n->{1 + f(n + 1)}! f(0)")
+            .unwrap_err()
+            .error,
+        Error::LimitExceeded(Limit::Depth, 20)
+    );
+    // `eval` counts as a call too, and the error points at the outermost one
+    let src = "const const e = `eval(e)`! eval(e)";
+    let err = crate::Interpreter::with_limits(Limits {
+        max_depth: Some(20),
+        ..Limits::default()
+    })
+    .eval_str(src)
+    .unwrap_err();
+    assert_eq!(err.error, Error::LimitExceeded(Limit::Depth, 20));
+    assert_eq!(err.span, Some(Span::new(27, src.len())));
+    // calls at the end of a function don't go any deeper
+    assert_eq!(
+        depth("const const g = n->{if(n > 0, g(n - 1), `done`)}! g(1000)"),
+        Ok(Value::from("done"))
+    );
+    assert_eq!(
+        depth("const const h = n->{if(n > 0, 1 + h(n - 1), 0)}! h(15)"),
+        Ok(Value::from(15.0))
+    );
+    // an object whose `call` leads back to itself isn't a function, instead of calling itself forever
+    for src in [
+        "var var a = {}! a.call = a! a()!",
+        "var var a = {}! var var b = {call: a}! a.call = b! b()!",
+        "var var a = {}! a.call = a! var var x = 1! next(x, a)! x = 2!",
+    ] {
+        assert!(matches!(
+            depth(src).unwrap_err().error,
+            Error::NotAFunction(_)
+        ));
+    }
+    assert_eq!(
+        depth("var var a = {}! var var b = {call: a}! a.call = ()->{self == a}! b()"),
+        Ok(Value::from(true))
+    );
+
+    let steps = limited(Limits {
+        max_steps: Some(1000),
+        ..Limits::default()
+    });
+    assert_eq!(
+        steps("while(true, {})").unwrap_err().error,
+        Error::LimitExceeded(Limit::Steps, 1000)
+    );
+    assert_eq!(
+        steps("while(true, eval(`1 + 1`))").unwrap_err().error,
+        Error::LimitExceeded(Limit::Steps, 1000)
+    );
+    // each run starts over, and the limit can't be caught
+    assert_eq!(steps("1 + 1"), Ok(Value::from(2.0)));
+    assert_eq!(
+        steps("try(while(true, {}), 0)").unwrap_err().error,
+        Error::LimitExceeded(Limit::Steps, 1000)
+    );
+
+    let objects = limited(Limits {
+        max_objects: Some(10),
+        ..Limits::default()
+    });
+    assert_eq!(
+        objects("var var i = 0! while(true, {[i]! i += 1})")
+            .unwrap_err()
+            .error,
+        Error::LimitExceeded(Limit::Objects, 10)
+    );
+    // fields and elements added to existing objects and arrays count too, and so do `use` objects
+    for src in [
+        "const var o = {}! var var i = 0! while(true, {o[i] = 1! i += 1})",
+        "const var o = {}! var var i = 0! while(true, {o[i]! i += 1})",
+        "const var a = []! var var i = 0! while(true, {a[i] = 1! i += 1})",
+        "const var a = [1]! var var i = 0.5! while(true, {a[i] = 1})",
+        "while(true, use(0))",
+        // in every file, not just the first one
+        "=====\nvar var i = 0! while(i < 1000, {use(0)! i += 1})",
+    ] {
+        assert_eq!(
+            objects(src).unwrap_err().error,
+            Error::LimitExceeded(Limit::Objects, 10),
+            "{src}"
+        );
+    }
+    // changing a field that's already there doesn't
+    assert_eq!(
+        objects("const var o = {a: 1}! var var i = 0! while(i < 100, {o.a += 1! i += 1})! o.a"),
+        Ok(Value::from(101.0))
+    );
+}
+
+#[test]
+fn nesting_limit() {
+    // code nested too deeply stops before it overflows the stack, whether it's parsed or run
+    let nesting = limited(Limits {
+        max_nesting: Some(100),
+        ..Limits::default()
+    });
+    for src in [
+        format!("{}1{}", "[".repeat(10_000), "]".repeat(10_000)),
+        format!("{}1", ";".repeat(10_000)),
+        format!("const const x: a{} = 1", "<a".repeat(10_000)),
+        format!("{}1{}", "x[0](".repeat(10_000), ")".repeat(10_000)),
+        "const const f = n->{1 + f(n + 1)}! f(0)".to_owned(),
+        format!("eval(`{}1{}`)", "(".repeat(60), ")".repeat(60)),
+    ] {
+        assert_eq!(
+            nesting(&src).unwrap_err().error,
+            Error::LimitExceeded(Limit::Nesting, 100),
+            "{src}"
+        );
+    }
+    assert_eq!(
+        nesting(&format!("{}1{}", "[".repeat(20), "]".repeat(20))).map(|value| value.to_string()),
+        Ok(format!("{}1{}", "[".repeat(20), "]".repeat(20)))
+    );
+}
+
+#[test]
+fn lifetimes() {
+    // lines count from the one after the declaration
+    assert_eq_db!(
+        "const const name<2> = `Luke`!\nconst var a = name!\nconst var b = name!\nconst var c = name!\n[a, b, c]",
+        "[`Luke`, `Luke`, undefined]"
+    );
+    // then the variable goes back to what it shadowed
+    assert_eq_db!(
+        "const const name = `Lu`!\nconst const name<1> = `Luke`!\nconst var a = name!\nconst var b = name!\n[a, b]",
+        "[`Luke`, `Lu`]"
+    );
+    // it's still around for the rest of its own line
+    assert_eq_db!("const const x = 1! { const const x<0> = 2! x }", "2");
+    assert_eq_db!("const const x = 1! { const const x<0> = 2!\nx }", "1");
+    // statements on the same line count as one line
+    assert_eq_db!(
+        "const const x<1> = 1! var var i = 0! while(i < 3, {i++!})!\nconst var a = x!\nconst var b = x!\n[a, b]",
+        "[1, undefined]"
+    );
+    // only the lines of the block it's declared in count, not the ones in functions it calls
+    assert_eq_db!(
+        "const const f = () -> {\n1!\n2!\n3!\n}!\nconst const x<1> = 1!\nf()!\nx",
+        "undefined"
+    );
+    assert_eq_db!(
+        "const const f = () -> {\n1!\n2!\n3!\n}!\nconst const x<2> = 1!\nf()!\nx",
+        "1"
+    );
+    // blank lines count too
+    assert_eq_db!("const const x<2> = 1!\n\n\nx", "undefined");
+    assert_eq_db!("const const x<Infinity> = 1! 1! 2! 3! x", "1");
+    assert_eq!(
+        eval("const const x<never> = 1!").unwrap_err().error,
+        Error::Expected(
+            "a lifetime like `<2>`, `<20s>`, or `<Infinity>`",
+            Some(Token::Ident("never".into()))
+        )
+    );
+
+    let interpreter = crate::Interpreter::new();
+    let clock = interpreter.clock();
+    *clock.borrow_mut() = Clock::manual();
+    interpreter
+        .eval_str("const const name = `Lu`! const const name<20s> = `Luke`!")
+        .unwrap();
+    let name = || interpreter.eval_str("name").unwrap().clone_inner();
+    clock
+        .borrow_mut()
+        .advance(std::time::Duration::from_secs(19));
+    assert_eq!(name(), Value::from("Luke"));
+    clock
+        .borrow_mut()
+        .advance(std::time::Duration::from_secs(1));
+    assert_eq!(name(), Value::from("Lu"));
+    // a lifetime too long to add to the clock lasts forever
+    interpreter
+        .eval_str("const const name<18446744073709551615s> = `Luke`!")
+        .unwrap();
+    clock
+        .borrow_mut()
+        .advance(std::time::Duration::from_secs(1_000_000));
+    assert_eq!(name(), Value::from("Luke"));
+
+    // each call runs on the lines after the last one
+    interpreter.eval_str("const const n<1> = 1!").unwrap();
+    let n = || interpreter.eval_str("n").unwrap().clone_inner();
+    assert_eq!(n(), Value::from(1.0));
+    assert_eq!(n(), Value::empty_object());
+}
+
+#[test]
+fn hoisting() {
+    assert_eq_db!(
+        "const var seen = name!\nconst const name<-1> = `Luke`!\nseen",
+        "`Luke`"
+    );
+    // it stays around after the line it's written on
+    assert_eq_db!("const const name<-1> = `Luke`!\nname", "`Luke`");
+    // it only moves as far as it says to
+    assert_eq_db!(
+        "const var a = x!\nconst var b = x!\nconst const x<-1> = 1!\n[a, b]",
+        "[`x`, 1]"
+    );
+    assert_eq_db!(
+        "const var a = x!\nconst var b = x!\nconst const x<-5> = 1!\n[a, b]",
+        "[1, 1]"
+    );
+    // it moves by lines, not statements
+    assert_eq_db!(
+        "const var a = x! const var b = x!\nconst const x<-1> = 1!\n[a, b]",
+        "[1, 1]"
+    );
+    // declarations hoisted to the same line keep their order
+    assert_eq_db!(
+        "const var a = x!\nconst const x<-1> = 1!\nconst const x<-2> = 2!\na",
+        "2"
+    );
+    // inside of functions too
+    assert_eq_db!(
+        "const const f = ()->{const var r = y!\nconst const y<-1> = 3!\nr}! f()",
+        "3"
+    );
+    assert_eq!(
+        eval("const const x<-a> = 1!").unwrap_err().error,
+        Error::Expected("a number of lines", Some(Token::Ident("a".into())))
+    );
+}
+
+#[test]
+fn const_const_const() {
+    // visible from every block and function, and can't be shadowed
+    assert_eq_db!(
+        "{const const const pi = 3!} const const f = ()->{pi}! const const pi = 4! [pi, f()]",
+        "[3, 3]"
+    );
+    // and can't be changed
+    assert_eq_db!(
+        "const const const name = `Luke`! try(name = `John`, 0)! try(name += `?`, 0)! name",
+        "`Luke`"
+    );
+    assert_eq_db!(
+        "const const const name = `Luke`! delete(name)! name",
+        "`Luke`"
+    );
+    assert_eq!(
+        eval("const const const a = 1! {const const const a = 2!}")
+            .unwrap_err()
+            .error,
+        Error::Redeclared("a".into())
+    );
+    // pointing at the second declaration
+    let err = crate::Interpreter::new()
+        .eval_str("const const const a = 1!\nconst const const a = 2!")
+        .unwrap_err();
+    assert_eq!(err.span, Some(Span::new(25, 44)));
+    // `const const const = ...` is still a `const const` called `const`
+    let interpreter = crate::Interpreter::new();
+    interpreter.eval_str("const const const = 5!").unwrap();
+    assert_eq!(interpreter.get_global("const").unwrap(), Value::from(5.0));
+
+    let interpreter = crate::Interpreter::new();
+    let src = "const const const greeting = `hi`!\n===== other.db ==\ngreeting";
+    assert_eq!(interpreter.eval_str(src).unwrap(), Value::from("hi"));
+    // later runs see it too, like in the REPL
+    assert_eq!(interpreter.eval_str("greeting").unwrap(), Value::from("hi"));
+    assert_eq!(
+        interpreter.get_global("greeting").unwrap(),
+        Value::from("hi")
+    );
+    assert!(interpreter
+        .eval_str("const const const greeting = `bye`!")
+        .is_err());
+}
+
+#[test]
+fn history() {
+    assert_eq_db!(
+        "var var score = 1! score = 2! score += 3! [previous(score), previous(score, 2), current(score)]",
+        "[2, 1, 5]"
+    );
+    // never changed, or not that many times
+    assert_eq_db!("var var score = 1! previous(score)", "undefined");
+    assert_eq_db!(
+        "var var score = 1! score = 2! previous(score, 3)",
+        "undefined"
+    );
+    // things that don't change the variable aren't remembered
+    assert_eq_db!(
+        "var const score = 1! score += 1! previous(score)",
+        "undefined"
+    );
+    assert_eq_db!(
+        "const var count = 1! count++! ++count! try(count = 5, 0)! [previous(count), count]",
+        "[2, 3]"
+    );
+    // changes from inside of a function count too
+    assert_eq_db!(
+        "var var n = 1! const const set = v->{n = v}! set(2)! set(3)! previous(n)",
+        "2"
+    );
+    // a new declaration starts over
+    assert_eq_db!(
+        "var var n = 1! n = 2! var var n = 3! previous(n)",
+        "undefined"
+    );
+    // `current` doesn't change with the variable
+    assert_eq_db!(
+        "var var n = 1! const const then = current(n)! n = 2! then",
+        "1"
+    );
+    // `next` runs once, on the next change
+    assert_eq_db!(
+        "var var n = 1! var var calls = 0! next(n, v->{calls += 1})! n = 2! n = 3! calls",
+        "1"
+    );
+    assert_eq_db!(
+        "var var n = 1! var var seen = 0! next(n, v->{seen = v*10})! n += 4! seen",
+        "50"
+    );
+    // waiting callbacks carry over when the variable is declared again
+    assert_eq_db!(
+        "var var n = 1! var var seen = 0! next(n, v->{seen = v})! var var n = 2! n = 3! seen",
+        "3"
+    );
+    // and there has to be a variable to wait for
+    assert_eq!(
+        eval("next(later, v->{print(v)})! var var later = 0! later = 1!")
+            .unwrap_err()
+            .error,
+        Error::NotDeclared("later".into())
+    );
+    assert!(eval("next(n)!").is_err());
+    assert!(eval("previous(1 + 1)!").is_err());
+}
+
+#[test]
+fn watchers() {
+    assert_eq_db!(
+        "var var health = 3! var var lost = 0! when(health ==== 0, lost += 1)! health = 1! health -= 1! health = 0! lost",
+        "2"
+    );
+    // not when it's declared, only when it changes
+    assert_eq_db!(
+        "var var health = 0! var var lost = 0! when(health ==== 0, lost += 1)! lost",
+        "0"
+    );
+    // every variable in the condition is watched, including objects whose fields change
+    assert_eq_db!(
+        "var var a = 0! var var b = 0! var var runs = 0! when(a + b > 0, runs += 1)! a = 1! b = 1! runs",
+        "2"
+    );
+    assert_eq_db!(
+        "const var player = {health: 1}! var var lost = 0! when(player.health ==== 0, lost = 1)! player.health = 0! lost",
+        "1"
+    );
+    // changes through `self` or a parameter count, since they're the same object
+    assert_eq_db!(
+        "const var player = {health: 5, hurt: ()->{self.health -= 3}}! var var lost = 0! when(player.health < 0, lost = 1)! player.hurt()! player.hurt()! lost",
+        "1"
+    );
+    assert_eq_db!(
+        "const var player = {health: 5}! const const hurt = p->{p.health -= 3}! var var lost = 0! when(player.health < 0, lost = 1)! hurt(player)! hurt(player)! lost",
+        "1"
+    );
+    // a redeclared variable is still watched, and so is one declared after the block
+    assert_eq_db!(
+        "var var n = 0! var var runs = 0! when(n > 0, runs += 1)! var var n = 0! n = 1! runs",
+        "1"
+    );
+    assert_eq_db!(
+        "var var runs = 0! when(later > 0, runs += 1)! var var later = 0! later = 1! runs",
+        "1"
+    );
+    // but not one that shadows it somewhere the block can't see
+    assert_eq_db!(
+        "var var n = 0! var var runs = 0! when(n > 0, runs += 1)! {var var n = 0! n = 1!} runs",
+        "0"
+    );
+    // a block that changes what it watches doesn't set itself off again
+    assert_eq_db!(
+        "var var n = 0! var var runs = 0! when(n > 0, {runs += 1! n += 1!})! n = 1! [n, runs]",
+        "[2, 1]"
+    );
+    // or go back and forth with another block
+    assert_eq_db!(
+        "var var a = 0! var var b = 0! when(a > 0, b += 1)! when(b > 0, a += 1)! a = 1! [a, b]",
+        "[2, 1]"
+    );
+    // and can't stop the loop or function that set it off, and neither can `next` callbacks
+    for (src, kw) in [
+        (
+            "var var n = 0! when(n > 0, break())! while(true, n += 1)",
+            Keyword::Break,
+        ),
+        (
+            "var var n = 0! when(n > 0, continue())! while(n < 3, n += 1)",
+            Keyword::Continue,
+        ),
+        (
+            "var var n = 0! when(n > 0, return(1))! const const f = ()->{n = 1! 2}! f()",
+            Keyword::Return,
+        ),
+        (
+            "var var n = 0! next(n, v->{break()})! while(true, n += 1)",
+            Keyword::Break,
+        ),
+    ] {
+        assert_eq!(eval(src).unwrap_err().error, Error::Misplaced(kw), "{src}");
+    }
+    assert_eq!(
+        eval("when(true)!").unwrap_err().error,
+        Error::InvalidArguments(Keyword::When, vec!["true".into()])
+    );
+}
+
+#[test]
+fn teardown() {
+    let marker = Rc::new(());
+    let interpreter = crate::Interpreter::new();
+    let held = marker.clone();
+    interpreter.register_native("marker", move |_| {
+        let _ = &held;
+        Ok(Pointer::from(1.0))
+    });
+    let src = "
+        const const f = ()->{f}!
+        const const make = ()->{const const g = ()->{g()}! ()->{marker}}!
+        const const h = make()!
+        var var n = 0!
+        when(n > 0, marker())!
+        next(n, v->{marker})!
+        const const const forever = ()->{marker}!
+        ===== other.db ==
+        const const e = ()->{e}!
+        export e to \"main.db\"!
+    ";
+    interpreter.eval_str(src).unwrap();
+    let state = Rc::downgrade(&interpreter.state);
+    // clones share the state, so it's only torn down once they're all gone
+    let clone = interpreter.clone();
+    drop(interpreter);
+    assert_eq!(Rc::strong_count(&marker), 2);
+    drop(clone);
+    assert_eq!(Rc::strong_count(&marker), 1);
+    assert!(state.upgrade().is_none());
 }
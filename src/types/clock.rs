@@ -0,0 +1,43 @@
+use std::time::{Duration, Instant};
+
+/// How long a program has been running, for variable lifetimes in seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    /// When the clock started, or `None` if it only moves with `Clock::advance`
+    start: Option<Instant>,
+    offset: Duration,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock {
+    /// Make a clock that follows real time
+    pub fn new() -> Self {
+        Self {
+            start: Some(Instant::now()),
+            offset: Duration::ZERO,
+        }
+    }
+
+    /// Make a clock that's stopped, so time only passes when you call `Clock::advance`. Useful for tests.
+    pub const fn manual() -> Self {
+        Self {
+            start: None,
+            offset: Duration::ZERO,
+        }
+    }
+
+    /// Move the clock forward by `time`
+    pub fn advance(&mut self, time: Duration) {
+        self.offset += time;
+    }
+
+    /// How long the clock has been running
+    pub fn elapsed(&self) -> Duration {
+        self.start.map_or(Duration::ZERO, |start| start.elapsed()) + self.offset
+    }
+}
@@ -0,0 +1,83 @@
+use std::fmt::{Display, Write};
+
+use super::{Error, Span};
+
+/// An error, optionally pointing at the source that caused it
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Diagnostic {
+    pub error: Error,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub const fn new(error: Error) -> Self {
+        Self { error, span: None }
+    }
+
+    /// Point the diagnostic at `span`, replacing any previous location
+    #[must_use]
+    pub const fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Point the diagnostic at `span` if it doesn't already point somewhere more specific
+    #[must_use]
+    pub fn or_span(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    /// Render the diagnostic with the offending line of `source` and a caret underline
+    ///
+    /// ```text
+    /// error: Can't assign to a `const const`
+    ///  --> main.db:2:6
+    ///   |
+    /// 2 | name = "John"!
+    ///   |      ^
+    /// ```
+    pub fn render(&self, name: &str, source: &str) -> String {
+        let mut out = format!("error: {}", self.error);
+        let Some(span) = self.span else {
+            let _ = write!(out, "\n --> {name}");
+            return out;
+        };
+        let (line, col) = span.line_col(source);
+        let text = source.lines().nth(line - 1).unwrap_or_default();
+        let gutter = " ".repeat(line.to_string().len());
+        let _ = write!(
+            out,
+            "\n{gutter}--> {name}:{line}:{col}\n{gutter} |\n{line} | {text}\n{gutter} | "
+        );
+        // keep tabs so the caret lines up with the source
+        for char in text.chars().take(col - 1) {
+            out.push(if char == '\t' { '\t' } else { ' ' });
+        }
+        let width = source
+            .get(span.start..span.end)
+            .and_then(|text| text.lines().next())
+            .map_or(0, |text| text.chars().count());
+        out.push_str(&"^".repeat(width.max(1)));
+        out
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for Diagnostic {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // `self.error` has the same message as `self`, so skip straight to what caused it
+        self.error.source()
+    }
+}
+
+impl From<Error> for Diagnostic {
+    fn from(value: Error) -> Self {
+        Self::new(value)
+    }
+}
@@ -0,0 +1,181 @@
+use std::{fmt::Display, io::ErrorKind, rc::Rc};
+
+use super::{Diagnostic, Keyword, Limit, Pointer, Syntax, Token};
+
+/// Everything that can go wrong while lexing, parsing, or interpreting
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Error {
+    /// The source ended in the middle of a token or expression
+    UnexpectedEof,
+    /// A token that doesn't make sense where it was found
+    UnexpectedToken(Token),
+    /// The parser wanted something specific and got something else (or nothing)
+    Expected(&'static str, Option<Token>),
+    /// A function parameter that isn't an identifier, written out as code
+    InvalidParameter(Rc<str>),
+    /// A built-in keyword was called with the wrong arguments, each written out as code
+    InvalidArguments(Keyword, Vec<Rc<str>>),
+    /// Assigning to a `const const` or `const var`, or editing an element of a value-const array
+    AssignToConst(Pointer),
+    /// Calling something that isn't a function and has no `call` field
+    NotAFunction(Pointer),
+    /// `a.b` where both sides are numbers, but they don't make a number together
+    InvalidNumber(Rc<str>),
+    /// An error from inside of an `eval`
+    Eval(Box<Diagnostic>),
+    /// A source file couldn't be read
+    Io(Rc<str>, ErrorKind),
+    /// An error returned by a `NativeFunction`
+    Native(Rc<str>),
+    /// An error from inside of an imported file
+    Import(Rc<str>, Box<Diagnostic>),
+    /// A file that imports itself, directly or through other files
+    ImportCycle(Rc<str>),
+    /// `import("file")` where the file is somewhere imports aren't allowed to read
+    ImportNotAllowed(Rc<str>),
+    /// `import x!` where nothing called `x` was exported to this file
+    NotExported(Rc<str>),
+    /// `break()`, on its way out to the loop it stops
+    Break,
+    /// `continue()`, on its way out to the loop it skips ahead
+    Continue,
+    /// `return(value)`, on its way out to the function it returns from
+    Return(Pointer),
+    /// `break()` or `continue()` that got out of the function it was in without finding a loop, or a signal that got
+    /// out of somewhere else it can't leave
+    Misplaced(Keyword),
+    /// A value thrown with `throw(value)` that no `try` caught
+    Thrown(Pointer),
+    /// Declaring a `const const const` that's already been declared
+    Redeclared(Rc<str>),
+    /// `next(x, callback)` where nothing called `x` has been declared
+    NotDeclared(Rc<str>),
+    /// The program went over one of the interpreter's `Limits`
+    LimitExceeded(Limit, usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEof => write!(f, "Unexpected end of file"),
+            Self::UnexpectedToken(tok) => write!(f, "Unexpected token `{tok:?}`"),
+            Self::Expected(expected, Some(found)) => {
+                write!(f, "Expected {expected}, got `{found:?}`")
+            }
+            Self::Expected(expected, None) => {
+                write!(f, "Expected {expected}, got end of file")
+            }
+            Self::InvalidParameter(syn) => {
+                write!(f, "Function input can only be identifiers, not `{syn}`")
+            }
+            Self::InvalidArguments(kw, args) => invalid_arguments(f, *kw, &args.join(", ")),
+            Self::AssignToConst(ptr @ Pointer::ConstVar(_)) => {
+                write!(f, "Can't assign to a `const var` {ptr:?}")
+            }
+            Self::AssignToConst(ptr @ Pointer::VarConst(_)) => {
+                write!(f, "Can't edit a `var const` {ptr:?}")
+            }
+            Self::AssignToConst(ptr) => write!(f, "Can't assign to a `const const` {ptr:?}"),
+            Self::NotAFunction(ptr) => write!(f, "`{ptr:?}` is not a function"),
+            Self::InvalidNumber(num) => write!(f, "`{num}` is not a valid number"),
+            Self::Eval(inner) => write!(f, "Error in `eval`: {inner}"),
+            Self::Io(path, kind) => write!(f, "Couldn't read `{path}`: {kind}"),
+            Self::Native(message) => write!(f, "{message}"),
+            Self::Import(path, inner) => write!(f, "Error in `{path}`: {inner}"),
+            Self::ImportCycle(path) => write!(f, "`{path}` imports itself"),
+            Self::ImportNotAllowed(path) => write!(f, "Not allowed to import `{path}`"),
+            Self::NotExported(name) => {
+                write!(f, "Nothing called `{name}` was exported to this file")
+            }
+            Self::Break => write!(f, "`break` can only be used in a loop"),
+            Self::Continue => write!(f, "`continue` can only be used in a loop"),
+            Self::Return(_) | Self::Misplaced(Keyword::Return) => {
+                write!(f, "`return` can only be used in a function")
+            }
+            Self::Misplaced(kw @ (Keyword::Break | Keyword::Continue)) => {
+                write!(f, "`{kw}` can only be used in a loop")
+            }
+            Self::Misplaced(kw) => write!(f, "`{kw}` can't be used here"),
+            Self::Thrown(value) => write!(f, "Uncaught error: {value}"),
+            Self::Redeclared(name) => {
+                write!(
+                    f,
+                    "`{name}` is a `const const const`, so it can't be declared again"
+                )
+            }
+            Self::NotDeclared(name) => write!(
+                f,
+                "Nothing called `{name}` has been declared, so `next` can't wait for it"
+            ),
+            Self::LimitExceeded(Limit::Depth, max) => {
+                write!(
+                    f,
+                    "Stack overflow: went over the limit of {max} nested calls"
+                )
+            }
+            Self::LimitExceeded(limit, max) => write!(f, "Went over the limit of {max} {limit}"),
+        }
+    }
+}
+
+/// Explain what arguments `kw` takes, since it got `args` instead
+fn invalid_arguments(f: &mut std::fmt::Formatter<'_>, kw: Keyword, args: &str) -> std::fmt::Result {
+    match kw {
+        Keyword::If => write!(f, "If statement requires two arguments: condition and body"),
+        Keyword::Function => write!(
+            f,
+            "Invalid arguments for `function`: `{args}`; expected name, args, and body"
+        ),
+        Keyword::Eval => write!(f, "You can only `eval` one thing at a time; got `{args}`"),
+        Keyword::While => write!(f, "While loop requires two arguments: condition and body"),
+        Keyword::When => write!(
+            f,
+            "When statement requires two arguments: condition and body"
+        ),
+        Keyword::Import => write!(f, "`import` takes one file path; got `{args}`"),
+        Keyword::Return => write!(f, "You can only `return` one thing at a time; got `{args}`"),
+        Keyword::Try => write!(f, "Try statement requires two arguments: body and handler"),
+        Keyword::Previous => write!(
+            f,
+            "`previous` takes a variable, and optionally how far back to look; got `{args}`"
+        ),
+        Keyword::Next => write!(
+            f,
+            "`next` takes a variable and a function to call when it changes; got `{args}`"
+        ),
+        kw => write!(f, "Invalid arguments for `{kw}`: `{args}`"),
+    }
+}
+
+impl Error {
+    /// Make an `Error::InvalidArguments`, writing out each argument as code
+    pub(crate) fn invalid_arguments(kw: Keyword, args: &[Syntax]) -> Self {
+        Self::InvalidArguments(kw, args.iter().map(|arg| arg.to_string().into()).collect())
+    }
+
+    /// Whether this is `break`, `continue`, or `return` passing through instead of something going wrong
+    pub const fn is_signal(&self) -> bool {
+        matches!(self, Self::Break | Self::Continue | Self::Return(_))
+    }
+
+    /// Turn a signal into an error, for when it's gotten somewhere it can't go any further, so that it doesn't stop a
+    /// loop or function further out
+    #[must_use]
+    pub fn misplaced(self) -> Self {
+        match self {
+            Self::Break => Self::Misplaced(Keyword::Break),
+            Self::Continue => Self::Misplaced(Keyword::Continue),
+            Self::Return(_) => Self::Misplaced(Keyword::Return),
+            other => other,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Eval(inner) | Self::Import(_, inner) => Some(inner.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::{cell::Cell, collections::VecDeque, rc::Rc};
+
+use super::{Pointer, Scope, Syntax, Value};
+
+/// Everything a variable has been, and the `next` callbacks waiting for it to change
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct History {
+    /// Values it used to have, newest first
+    previous: VecDeque<Rc<Value>>,
+    /// Functions to call with its new value the next time it changes
+    next: Vec<Pointer>,
+}
+
+/// A `when` block, waiting for the variables in its condition to change
+#[derive(Debug, PartialEq, Eq)]
+pub struct Watcher {
+    pub condition: Syntax,
+    pub body: Syntax,
+    /// The scope `when` was called in, which the condition and body run in
+    pub scope: Scope,
+    /// The variables the condition reads
+    idents: Vec<Rc<str>>,
+    running: Cell<bool>,
+}
+
+impl Watcher {
+    pub fn new(condition: Syntax, body: Syntax, scope: Scope) -> Self {
+        Self {
+            idents: condition.idents().into_iter().cloned().collect(),
+            condition,
+            body,
+            scope,
+            running: Cell::new(false),
+        }
+    }
+
+    /// Whether `ptr` changing could change the condition. The variables in it are looked up again every time, so they
+    /// can be redeclared, and they match `ptr` if it can change them, like a parameter or `self` pointing to the same
+    /// object.
+    pub fn watches(&self, ptr: &Pointer) -> bool {
+        let scope = self.scope.0.borrow();
+        self.idents.iter().any(|ident| {
+            scope
+                .lookup(ident)
+                .is_some_and(|watched| watched.shares_cell(ptr))
+        })
+    }
+
+    /// Mark the watcher as running. Returns `false` if it already was, since running it again from inside of itself
+    /// could go on forever.
+    pub const fn start(&self) -> bool {
+        !self.running.replace(true)
+    }
+
+    /// Mark the watcher as done running
+    pub fn finish(&self) {
+        self.running.set(false);
+    }
+}
+
+impl History {
+    /// How many old values to remember, so long loops don't keep everything they've been through
+    pub const MAX_LEN: usize = 100;
+
+    /// Remember a value the variable just stopped being, and take the callbacks that were waiting for it to change
+    pub fn record(&mut self, old: Rc<Value>) -> Vec<Pointer> {
+        self.previous.push_front(old);
+        self.previous.truncate(Self::MAX_LEN);
+        core::mem::take(&mut self.next)
+    }
+
+    /// Get the value from `steps` changes ago, if it's been changed that many times
+    pub fn previous(&self, steps: usize) -> Option<Rc<Value>> {
+        self.previous.get(steps.checked_sub(1)?).cloned()
+    }
+
+    /// Call `callback` with the new value the next time the variable changes
+    pub fn wait(&mut self, callback: Pointer) {
+        self.next.push(callback);
+    }
+
+    /// Take over the callbacks waiting on `old`, for when the variable is declared again. Its old values stay behind.
+    pub fn inherit(&mut self, old: &mut Self) {
+        self.next.append(&mut old.next);
+    }
+}
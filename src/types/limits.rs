@@ -0,0 +1,119 @@
+use std::fmt::Display;
+
+use super::Error;
+
+/// How much a program is allowed to do before it gets stopped. `None` means there's no limit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// How many function calls can be running at once. Calls at the end of a function don't count.
+    pub max_depth: Option<usize>,
+    /// How many expressions can be evaluated in one run
+    pub max_steps: Option<usize>,
+    /// How many objects and arrays can be made in one run. This counts literals, `use` objects, and every field or
+    /// element added to an existing object or array.
+    pub max_objects: Option<usize>,
+    /// How deeply code can be nested, both when it's parsed and when it runs. Every expression inside of another one
+    /// is a level, and a function call takes a few levels.
+    ///
+    /// This is what keeps a program from overflowing the Rust stack, so make sure the thread running the interpreter has
+    /// room for it. Each level can take up to 16 KB in a debug build, or 2 KB in a release build. The main thread
+    /// usually has 8 MB, which fits 500 levels in a debug build.
+    pub max_nesting: Option<usize>,
+}
+
+/// Which of the `Limits` a program went over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Depth,
+    Steps,
+    Objects,
+    Nesting,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Depth => write!(f, "nested calls"),
+            Self::Steps => write!(f, "steps"),
+            Self::Objects => write!(f, "objects"),
+            Self::Nesting => write!(f, "levels of nesting"),
+        }
+    }
+}
+
+/// The limits, and how much of each has been used so far. Every state an interpreter makes shares one of these.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Budget {
+    pub limits: Limits,
+    depth: usize,
+    steps: usize,
+    objects: usize,
+    nesting: usize,
+}
+
+impl Budget {
+    /// Start counting from zero again, for a new run
+    pub const fn reset(&mut self) {
+        self.depth = 0;
+        self.steps = 0;
+        self.objects = 0;
+        self.nesting = 0;
+    }
+
+    /// Count one expression being evaluated
+    ///
+    /// # Errors
+    /// Returns `Error::LimitExceeded` if that's more than `max_steps`
+    pub const fn step(&mut self) -> Result<(), Error> {
+        Self::count(&mut self.steps, self.limits.max_steps, Limit::Steps)
+    }
+
+    /// Count one object or array being made
+    ///
+    /// # Errors
+    /// Returns `Error::LimitExceeded` if that's more than `max_objects`
+    pub const fn allocate(&mut self) -> Result<(), Error> {
+        Self::count(&mut self.objects, self.limits.max_objects, Limit::Objects)
+    }
+
+    /// Count a function call starting. Every successful `enter` needs an `exit` once the call is done.
+    ///
+    /// # Errors
+    /// Returns `Error::LimitExceeded` if that's more than `max_depth`
+    pub const fn enter(&mut self) -> Result<(), Error> {
+        Self::count(&mut self.depth, self.limits.max_depth, Limit::Depth)
+    }
+
+    /// Count a function call finishing
+    pub const fn exit(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    /// Count going one level deeper into the code. Every successful `nest` needs an `unnest` once it's done.
+    ///
+    /// # Errors
+    /// Returns `Error::LimitExceeded` if that's more than `max_nesting`
+    pub const fn nest(&mut self) -> Result<(), Error> {
+        Self::count(&mut self.nesting, self.limits.max_nesting, Limit::Nesting)
+    }
+
+    /// Count coming back out of a level of the code
+    pub const fn unnest(&mut self) {
+        self.nesting = self.nesting.saturating_sub(1);
+    }
+
+    /// How many levels deep the code that's running right now is
+    pub const fn nesting(&self) -> usize {
+        self.nesting
+    }
+
+    const fn count(used: &mut usize, max: Option<usize>, limit: Limit) -> Result<(), Error> {
+        match max {
+            Some(max) if *used >= max => Err(Error::LimitExceeded(limit, max)),
+            _ => {
+                *used += 1;
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,6 +1,13 @@
 pub use prelude::*;
 
+mod clock;
+mod diagnostic;
+mod error;
+mod history;
+mod limits;
+mod module;
 mod pointer;
+mod span;
 mod state;
 mod syntax;
 mod token;
@@ -10,15 +17,22 @@ pub mod prelude {
     use std::cell::RefCell;
     use std::rc::Rc;
 
+    pub use super::clock::Clock;
+    pub use super::diagnostic::Diagnostic;
+    pub use super::error::Error;
+    pub use super::history::{History, Watcher};
+    pub use super::limits::{Budget, Limit, Limits};
+    pub use super::module::{Imports, Modules};
     pub use super::pointer::Pointer;
+    pub use super::span::Span;
     pub use super::state::State;
-    pub use super::syntax::{Operation, Syntax, VarType};
+    pub use super::syntax::{Lifetime, Operation, Syntax, VarType};
     pub use super::token::{StringSegment, Token};
-    pub use super::value::{Boolean, Keyword, Value};
+    pub use super::value::{Boolean, Function, Keyword, NativeFunction, Scope, Value};
 
-    pub type SResult<T> = Result<T, String>;
+    pub type SResult<T> = Result<T, Diagnostic>;
     pub type RcMut<T> = Rc<RefCell<T>>;
-    pub type OpGroup = (Syntax, Operation, u8);
+    pub type OpGroup = (Syntax, Operation, Span, u8);
 
     pub fn rc_mut_new<T>(content: T) -> RcMut<T> {
         Rc::new(RefCell::new(content))
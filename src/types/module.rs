@@ -0,0 +1,131 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+use super::{Error, Pointer, Value};
+
+/// Which files `import("file")` is allowed to read
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Imports {
+    /// Any file
+    #[default]
+    Anywhere,
+    /// Only files inside of this directory
+    Within(PathBuf),
+    /// No files at all
+    Disabled,
+}
+
+/// Everything the files run by one interpreter share: what they've exported to each other, and which ones have run.
+/// Paths are normalized first, so different ways of writing the same one match.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Modules {
+    /// exported variables, by the file they were exported to and their name
+    exports: HashMap<PathBuf, HashMap<Rc<str>, Pointer>>,
+    /// files that have started running, so each one only runs once
+    loaded: HashSet<PathBuf>,
+    /// files that are still running, to catch import cycles
+    loading: Vec<PathBuf>,
+    /// which files `import` can read
+    imports: Imports,
+}
+
+impl Modules {
+    /// Change which files `import` can read
+    pub fn set_imports(&mut self, imports: Imports) {
+        self.imports = imports;
+    }
+
+    /// Check that `import` can read the file at `path`. A path that gets out of the directory imports are allowed from,
+    /// through `..` or a symlink, can't be read.
+    ///
+    /// # Errors
+    /// Returns `Error::ImportNotAllowed` if it can't
+    pub fn check_import(&self, path: &Path) -> Result<(), Error> {
+        let allowed = match &self.imports {
+            Imports::Anywhere => true,
+            Imports::Disabled => false,
+            // check the path as it's written first, so nothing outside of `root` gets looked at
+            Imports::Within(root) => {
+                normalize(path).starts_with(normalize(root))
+                    && fs::canonicalize(path).map_or(true, |real| {
+                        fs::canonicalize(root).is_ok_and(|root| real.starts_with(root))
+                    })
+            }
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(Error::ImportNotAllowed(path.display().to_string().into()))
+        }
+    }
+
+    /// Make `ptr` available to `import name!` in the file at `to`
+    pub fn export(&mut self, to: &Path, name: Rc<str>, ptr: Pointer) {
+        self.exports
+            .entry(normalize(to))
+            .or_default()
+            .insert(name, ptr);
+    }
+
+    /// Get a variable that was exported to the file at `path`
+    pub fn import(&self, path: &Path, name: &str) -> Option<Pointer> {
+        self.exports.get(&normalize(path))?.get(name).cloned()
+    }
+
+    /// Get everything that was exported to the file at `path` as an object
+    #[allow(clippy::mutable_key_type)]
+    pub fn exports_to(&self, path: &Path) -> Value {
+        let exports = self.exports.get(&normalize(path)).into_iter().flatten();
+        Value::Object(
+            exports
+                .map(|(name, ptr)| (Value::from(name.clone()), ptr.clone()))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    /// Mark `path` as running
+    pub fn start(&mut self, path: &Path) {
+        let path = normalize(path);
+        self.loaded.insert(path.clone());
+        self.loading.push(path);
+    }
+
+    /// Mark `path` as done running. If it failed, importing it again runs it again instead of using what it got to.
+    pub fn finish(&mut self, path: &Path, succeeded: bool) {
+        let path = normalize(path);
+        self.loading.retain(|loading| *loading != path);
+        if !succeeded {
+            self.loaded.remove(&path);
+        }
+    }
+
+    /// Check if `path` has already started running
+    pub fn is_loaded(&self, path: &Path) -> bool {
+        self.loaded.contains(&normalize(path))
+    }
+
+    /// Check if `path` is still running, meaning that importing it again would be a cycle
+    pub fn is_loading(&self, path: &Path) -> bool {
+        self.loading.contains(&normalize(path))
+    }
+}
+
+/// Make `path` absolute and take out any `.` and `..`, without looking at the file system
+fn normalize(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normal = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            other => normal.push(other),
+        }
+    }
+    normal
+}
@@ -65,9 +65,10 @@ impl Pointer {
     }
 
     /// Convert this pointer to a different type. Performs a shallow clone if switching between inner `const` and `var`
+    #[must_use]
     pub fn convert(&self, vt: VarType) -> Self {
         match vt {
-            VarType::ConstConst => Self::ConstConst(self.as_const()),
+            VarType::ConstConst | VarType::ConstConstConst => Self::ConstConst(self.as_const()),
             VarType::ConstVar => Self::ConstVar(self.as_var()),
             VarType::VarConst => Self::VarConst(rc_mut_new(self.as_const())),
             VarType::VarVar => Self::VarVar(rc_mut_new(self.as_var())),
@@ -90,6 +91,7 @@ impl Pointer {
     /// 2. internal data must be identical with type coercion
     /// 3. internal data must be identical without type coercion
     /// 4. internal pointers must be identical
+    #[must_use]
     pub fn eq(&self, rhs: &Self, precision: u8) -> Self {
         if precision >= 4 {
             Self::from(match (self, rhs) {
@@ -112,37 +114,101 @@ impl Pointer {
     }
 
     /// Apply the dot operator. This has two valid cases: float parsing and object indexing. Otherwise, it returns `undefined`
-    #[allow(clippy::option_if_let_else, clippy::single_match_else)]
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidNumber` if two numbers don't make a valid number together
     pub fn dot(&self, rhs: &Value) -> SResult<Self> {
-        let allow_modify = matches!(self, Self::ConstVar(_) | Self::VarVar(_));
         let lhs = self.clone_inner();
         match (lhs, rhs) {
             (Value::Number(lhs), Value::Number(rhs)) => {
-                Ok(Self::from(format!("{lhs}.{rhs}").parse::<f64>().map_err(
-                    |e| format!("Error parsing `{lhs}.{rhs}`: {e}"),
-                )?))
+                let num = format!("{lhs}.{rhs}");
+                Ok(Self::from(
+                    num.parse::<f64>()
+                        .map_err(|_| Error::InvalidNumber(num.into()))?,
+                ))
             }
-            (Value::Object(mut obj), key) => match obj.get(key) {
-                Some(ptr) => Ok(ptr.clone()),
-                None => {
-                    let val = if allow_modify {
-                        Self::ConstVar(rc_mut_new(Value::empty_object()))
-                    } else {
-                        Self::ConstConst(Rc::new(Value::empty_object()))
-                    };
-                    obj.insert(key.clone(), val.clone());
-                    Ok(val)
-                }
-            },
+            (Value::Object(_), key) => Ok(self.field(key)),
             _ => Ok(Self::from(Value::empty_object())),
         }
     }
 
-    /// Try to replace the current value with given value. Returns `Err` if `self` is ptr-const. Doesn't clone if it's not necessary.
+    /// Get a field of an object, adding it as a `var var` `undefined` if it isn't there yet.
+    /// If `self` is value-const, the field is only added to a copy of the object.
+    #[must_use]
+    pub fn field(&self, key: &Value) -> Self {
+        let var = self.as_var();
+        let Value::Object(ref mut obj) = *var.borrow_mut() else {
+            return Self::from(Value::empty_object());
+        };
+        obj.entry(key.clone())
+            .or_insert_with(|| Self::from(Value::empty_object()).convert(VarType::VarVar))
+            .clone()
+    }
+
+    /// Get a field of an object like `Pointer::field`, or an element of an array, which start at `-1`.
+    /// Anything that isn't an element is `undefined`. Elements of a value-const array are copied, so they can't be edited.
+    #[must_use]
+    pub fn index(&self, idx: &Value) -> Self {
+        if self.with_ref(|val| matches!(val, Value::Object(_))) {
+            return self.field(idx);
+        }
+        let allow_modify = matches!(self, Self::ConstVar(_) | Self::VarVar(_));
+        let elem = self.with_ref(|val| match (val, idx) {
+            (Value::Array(arr), &Value::Number(idx)) => {
+                array_position(idx).and_then(|pos| arr.get(pos)).cloned()
+            }
+            _ => None,
+        });
+        match elem {
+            Some(elem) if allow_modify => elem,
+            Some(elem) => Self::ConstConst(elem.as_const()),
+            None => Self::from(Value::empty_object()),
+        }
+    }
+
+    /// Set a field of an object, or an element of an array. A whole number replaces the element there (or adds one to the end if it's past the end),
+    /// and anything else inserts a new element where that index would be, like between two elements.
+    ///
+    /// # Errors
+    /// Returns `Error::AssignToConst` if `self` is a value-const array
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn set_index(&self, idx: &Value, value: &Self) -> SResult<()> {
+        if self.with_ref(|val| matches!(val, Value::Object(_))) {
+            return self.field(idx).assign(value);
+        }
+        if !matches!(self, Self::ConstVar(_) | Self::VarVar(_)) {
+            return Err(Error::AssignToConst(self.clone()).into());
+        }
+        let &Value::Number(idx) = idx else {
+            return Ok(());
+        };
+        let value = value.convert(VarType::VarVar);
+        let var = self.as_var();
+        let Value::Array(ref mut arr) = *var.borrow_mut() else {
+            return Ok(());
+        };
+        if let Some(pos) = array_position(idx) {
+            match arr.get_mut(pos) {
+                Some(elem) => *elem = value,
+                None => arr.push(value),
+            }
+        } else if !idx.is_nan() {
+            // `idx + 1` is where the element would be, so it goes after everything before that
+            let pos = (idx + 1.0).ceil().max(0.0) as usize;
+            arr.insert(pos.min(arr.len()), value);
+        }
+        Ok(())
+    }
+
+    /// Try to replace the current value with given value. Doesn't clone if it's not necessary.
+    ///
+    /// # Errors
+    /// Returns `Error::AssignToConst` if `self` is ptr-const
     pub fn assign(&self, rhs: &Self) -> SResult<()> {
         match self {
-            Self::ConstConst(_) => Err(format!("Can't assign to a `const const` {self:?}")),
-            Self::ConstVar(_) => Err(format!("Can't assign to a `const var` {self:?}")),
+            Self::ConstConst(_) | Self::ConstVar(_) => {
+                Err(Error::AssignToConst(self.clone()).into())
+            }
             Self::VarConst(ptr) => {
                 ptr.replace(rhs.as_const());
                 Ok(())
@@ -178,6 +244,24 @@ impl Pointer {
         }
     }
 
+    /// Whether changing one of these pointers can change the other: they're the same variable, or they point to the
+    /// same mutable value
+    pub fn shares_cell(&self, other: &Self) -> bool {
+        let cells = other.cells();
+        self.cells().iter().any(|cell| cells.contains(cell))
+    }
+
+    /// The addresses of everything that can change through this pointer: the variable if it can be reassigned, and the
+    /// value if it can be edited
+    fn cells(&self) -> Vec<*const ()> {
+        match self {
+            Self::ConstConst(_) => Vec::new(),
+            Self::ConstVar(val) => vec![Rc::as_ptr(val).cast()],
+            Self::VarConst(var) => vec![Rc::as_ptr(var).cast()],
+            Self::VarVar(var) => vec![Rc::as_ptr(var).cast(), Rc::as_ptr(&*var.borrow()).cast()],
+        }
+    }
+
     /// Run a function on a reference to the internal value. This does not clone the internal value.
     pub fn with_ref<T, F: FnOnce(&Value) -> T>(&self, func: F) -> T {
         match self {
@@ -200,9 +284,15 @@ impl PartialEq<Value> for Pointer {
     }
 }
 
+/// What `<`, `<=`, `>`, and `>=` use. Numbers compare like floats usually do, so `NaN` isn't bigger or smaller than
+/// anything and `;0` is the same as `0`. Everything else is ordered like `Value`'s `Ord`, which sorts `NaN` last so it
+/// can be an object key.
 impl PartialOrd for Pointer {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.with_refs(other, std::cmp::PartialOrd::partial_cmp)
+        self.with_refs(other, |lhs, rhs| match (lhs, rhs) {
+            (Value::Number(lhs), Value::Number(rhs)) => lhs.partial_cmp(rhs),
+            _ => lhs.partial_cmp(rhs),
+        })
     }
 }
 
@@ -225,7 +315,7 @@ impl AddAssign for Pointer {
                 val.borrow().replace(output);
             }
             _ => {}
-        };
+        }
     }
 }
 
@@ -248,7 +338,7 @@ impl SubAssign for Pointer {
                 val.borrow().replace(output);
             }
             _ => {}
-        };
+        }
     }
 }
 
@@ -271,7 +361,7 @@ impl MulAssign for Pointer {
                 val.borrow().replace(output);
             }
             _ => {}
-        };
+        }
     }
 }
 
@@ -294,7 +384,7 @@ impl DivAssign for Pointer {
                 val.borrow().replace(output);
             }
             _ => {}
-        };
+        }
     }
 }
 
@@ -359,8 +449,27 @@ impl From<Rc<str>> for Pointer {
     }
 }
 
+impl From<String> for Pointer {
+    fn from(value: String) -> Self {
+        Self::ConstConst(Rc::new(Value::from(value)))
+    }
+}
+
+impl From<Boolean> for Pointer {
+    fn from(value: Boolean) -> Self {
+        Self::ConstConst(Rc::new(Value::Boolean(value)))
+    }
+}
+
 impl From<f64> for Pointer {
     fn from(value: f64) -> Self {
         Self::ConstConst(Rc::new(Value::Number(value)))
     }
 }
+
+/// Get the position in a `Vec` of an array index, if it's a whole number of at least `-1`
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn array_position(idx: f64) -> Option<usize> {
+    let pos = idx + 1.0;
+    (pos >= 0.0 && pos.fract() == 0.0).then_some(pos as usize)
+}
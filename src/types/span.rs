@@ -0,0 +1,26 @@
+/// A range of bytes in the source text
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy, Default, PartialOrd, Ord)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub const fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Get the smallest span containing both `self` and `other`
+    #[must_use]
+    pub fn to(self, other: Self) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    /// Get the 1-indexed line and column (in characters) of the start of the span
+    pub fn line_col(&self, source: &str) -> (usize, usize) {
+        let before = source.get(..self.start).unwrap_or(source);
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        (line, before[line_start..].chars().count() + 1)
+    }
+}
@@ -1,4 +1,10 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::{Rc, Weak},
+    time::Duration,
+};
 
 use lazy_regex::regex;
 
@@ -6,57 +12,234 @@ use crate::types::prelude::*;
 
 use core::f64::consts as f64;
 
+/// A variable, when it expires if it has a lifetime, and what it used to be
 #[derive(Debug, PartialEq)]
+struct Binding {
+    ptr: Pointer,
+    expires: Option<Expiry>,
+    history: RcMut<History>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Expiry {
+    /// The line of the scope it's declared in that it expires at
+    Line(usize),
+    /// The value of `Clock::elapsed` it expires at
+    Time(Duration),
+}
+
+impl Binding {
+    fn new(ptr: Pointer, expires: Option<Expiry>) -> Self {
+        Self {
+            ptr,
+            expires,
+            history: rc_mut_new(History::default()),
+        }
+    }
+
+    /// Whether its lifetime is up, if `line` is the line running in the scope it was declared in
+    fn is_expired(&self, clock: &Clock, line: usize) -> bool {
+        match self.expires {
+            None => false,
+            Some(Expiry::Line(expires)) => line >= expires,
+            Some(Expiry::Time(time)) => clock.elapsed() >= time,
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct State {
-    current: HashMap<Rc<str>, Pointer>,
-    parent: Option<RcMut<State>>,
+    /// Every variable in this scope. A variable with a lifetime is pushed on top of the ones it shadows, so they come
+    /// back once it expires.
+    current: HashMap<Rc<str>, Vec<Binding>>,
+    parent: Option<RcMut<Self>>,
     pub undefined: Pointer,
+    /// The file being run, which `import` and `export` paths are relative to
+    pub path: Rc<Path>,
+    pub modules: RcMut<Modules>,
+    pub budget: RcMut<Budget>,
+    pub clock: RcMut<Clock>,
+    /// The source line of the statement running in this scope, which lifetimes in lines count down with
+    pub line: usize,
+    /// `const const const` variables, which every scope and file can see, and nothing can shadow
+    pub globals: RcMut<HashMap<Rc<str>, Pointer>>,
+    /// Every state a function or `when` closed over. They hold those functions, which hold them, so they have to be
+    /// cleared by `State::tear_down` to be freed.
+    scopes: RcMut<Vec<Weak<RefCell<Self>>>>,
+    /// Every `when` block, which gets checked whenever something changes
+    pub watchers: RcMut<Vec<Rc<Watcher>>>,
+    /// Whether this state is in `scopes` yet
+    captured: bool,
 }
 
 macro_rules! kw {
-    ($current:ident $str:expr => $kw:expr) => {
-        $current.insert($str.into(), Pointer::from(Value::from($kw)))
+    ($state:ident $str:expr => $kw:expr) => {
+        $state.insert($str.into(), Pointer::from(Value::from($kw)))
     };
 }
 
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl State {
     pub fn new() -> Self {
-        let mut current = HashMap::new();
-        let undefined = Pointer::ConstConst(Rc::new(Value::empty_object()));
-        kw!(current "🥧" => f64::PI);
-        kw!(current "delete" => Keyword::Delete);
-        kw!(current "const" => Keyword::Const);
-        kw!(current "var" => Keyword::Var);
-        kw!(current "if" => Keyword::If);
-        kw!(current "eval" => Keyword::Eval);
-        kw!(current "true" => true);
-        kw!(current "false" => false);
-        kw!(current "maybe" => Boolean::Maybe);
-        kw!(current "infinity" => Value::Number(f64::INFINITY));
-        kw!(current "∞" => Value::Number(f64::INFINITY));
-        current.insert("undefined".into(), undefined.clone());
+        let mut state = Self::empty();
+        state.declare_builtins();
+        state
+    }
+
+    /// Make a global state with nothing declared in it, that doesn't share anything with another one
+    fn empty() -> Self {
         Self {
-            current,
+            current: HashMap::new(),
             parent: None,
-            undefined,
+            undefined: Pointer::ConstConst(Rc::new(Value::empty_object())),
+            path: Path::new("main.db").into(),
+            modules: rc_mut_new(Modules::default()),
+            budget: rc_mut_new(Budget::default()),
+            clock: rc_mut_new(Clock::default()),
+            line: 0,
+            globals: rc_mut_new(HashMap::new()),
+            scopes: rc_mut_new(Vec::new()),
+            watchers: rc_mut_new(Vec::new()),
+            captured: false,
         }
     }
 
+    /// Declare the keywords and the standard library. Built-ins like `use` count against this state's budget, so it
+    /// has to be the shared one by now.
+    fn declare_builtins(&mut self) {
+        let state = self;
+        kw!(state "🥧" => f64::PI);
+        kw!(state "delete" => Keyword::Delete);
+        kw!(state "const" => Keyword::Const);
+        kw!(state "var" => Keyword::Var);
+        kw!(state "if" => Keyword::If);
+        kw!(state "while" => Keyword::While);
+        kw!(state "break" => Keyword::Break);
+        kw!(state "continue" => Keyword::Continue);
+        kw!(state "return" => Keyword::Return);
+        kw!(state "throw" => Keyword::Throw);
+        kw!(state "try" => Keyword::Try);
+        kw!(state "eval" => Keyword::Eval);
+        kw!(state "import" => Keyword::Import);
+        kw!(state "previous" => Keyword::Previous);
+        kw!(state "current" => Keyword::Current);
+        kw!(state "next" => Keyword::Next);
+        kw!(state "when" => Keyword::When);
+        kw!(state "true" => true);
+        kw!(state "false" => false);
+        kw!(state "maybe" => Boolean::Maybe);
+        kw!(state "infinity" => Value::Number(f64::INFINITY));
+        kw!(state "∞" => Value::Number(f64::INFINITY));
+        state.insert("undefined".into(), state.undefined.clone());
+        crate::builtins::register(state);
+    }
+
+    /// Make a new global state for running the file at `path`, sharing modules and limits with `other`
+    pub fn for_file(path: PathBuf, other: &Self) -> Self {
+        let mut state = Self {
+            path: path.into(),
+            modules: other.modules.clone(),
+            budget: other.budget.clone(),
+            clock: other.clock.clone(),
+            globals: other.globals.clone(),
+            scopes: other.scopes.clone(),
+            watchers: other.watchers.clone(),
+            ..Self::empty()
+        };
+        state.declare_builtins();
+        state
+    }
+
     pub fn from_parent(parent: Rc<RefCell<Self>>) -> Self {
-        let undefined = parent.borrow().undefined.clone();
+        let (undefined, path, modules, budget, clock, globals, scopes, watchers) = {
+            let parent = parent.borrow();
+            (
+                parent.undefined.clone(),
+                parent.path.clone(),
+                parent.modules.clone(),
+                parent.budget.clone(),
+                parent.clock.clone(),
+                parent.globals.clone(),
+                parent.scopes.clone(),
+                parent.watchers.clone(),
+            )
+        };
         Self {
             current: HashMap::new(),
             undefined,
+            path,
+            modules,
+            budget,
+            clock,
+            line: 0,
+            globals,
+            scopes,
+            watchers,
+            captured: false,
             parent: Some(parent),
         }
     }
 
+    /// Remember that a function or `when` closed over `state`, so `State::tear_down` can clear it
+    pub fn capture(state: &RcMut<Self>) {
+        let mut inner = state.borrow_mut();
+        if inner.captured {
+            return;
+        }
+        inner.captured = true;
+        let mut scopes = inner.scopes.borrow_mut();
+        if scopes.len() == scopes.capacity() {
+            // forget the ones that have already been freed before making room for more
+            scopes.retain(|scope| scope.strong_count() > 0);
+        }
+        scopes.push(Rc::downgrade(state));
+    }
+
+    /// Forget every variable in every closed-over state, along with the `const const const`s, `when` blocks, and exports.
+    /// That breaks the cycles between states and the functions declared in them, so everything can be freed.
+    pub fn tear_down(&mut self) {
+        let scopes = core::mem::take(&mut *self.scopes.borrow_mut());
+        for scope in scopes.iter().filter_map(Weak::upgrade) {
+            // dropping the variables can free other states, so don't hold a borrow while it happens
+            let Ok(mut scope) = scope.try_borrow_mut() else {
+                continue;
+            };
+            let current = core::mem::take(&mut scope.current);
+            drop(scope);
+            drop(current);
+        }
+        self.current.clear();
+        let watchers = core::mem::take(&mut *self.watchers.borrow_mut());
+        drop(watchers);
+        let globals = core::mem::take(&mut *self.globals.borrow_mut());
+        drop(globals);
+        let modules = core::mem::take(&mut *self.modules.borrow_mut());
+        drop(modules);
+    }
+
+    /// Get the path to `file`, relative to the file being run
+    pub fn resolve(&self, file: &str) -> PathBuf {
+        self.path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(file)
+    }
+
     pub fn get(&mut self, key: Rc<str>) -> Pointer {
         // println!("{:?}: {key}", self.current);
-        // if there's a value here, get it
-        if let Some(val) = self.current.get(&key) {
+        // `const const const` variables can't be shadowed
+        if let Some(val) = self.globals.borrow().get(&key) {
             return val.clone();
         }
+        // if there's a value here, get it
+        if let Some(val) = self.live(&key) {
+            return val;
+        }
         // if there's a value in the parent, get it
         if let Some(parent) = &self.parent {
             return (**parent).borrow_mut().get(key);
@@ -64,33 +247,150 @@ impl State {
         // otherwise, parse it in global context
         if let Ok(val) = key.parse() {
             let new_val = Pointer::ConstConst(Rc::new(Value::Number(val)));
-            self.current.insert(key, new_val.clone());
+            self.insert(key, new_val.clone());
             new_val
         } else if regex!("^f?u?n?c?t?i?o?n?$").is_match(&key) {
             let v = Pointer::ConstConst(Rc::new(Value::Keyword(Keyword::Function)));
-            self.current.insert(key, v.clone());
+            self.insert(key, v.clone());
             v
         } else {
             let v = Pointer::ConstConst(Rc::new(Value::String(key.clone())));
-            self.current.insert(key, v.clone());
+            self.insert(key.clone(), v.clone());
             v
         }
     }
 
+    /// Get the newest variable called `key` in this scope that hasn't expired, forgetting the ones that have. Once
+    /// they've all expired, it's `undefined`, unless a parent scope has one to go back to.
+    fn live(&mut self, key: &str) -> Option<Pointer> {
+        let bindings = self.current.get_mut(key)?;
+        bindings.retain(|binding| !binding.is_expired(&self.clock.borrow(), self.line));
+        if let Some(binding) = bindings.last() {
+            return Some(binding.ptr.clone());
+        }
+        self.current.remove(key);
+        let shadowed = self
+            .parent
+            .as_ref()
+            .is_some_and(|parent| parent.borrow().lookup(key).is_some());
+        if shadowed {
+            return None;
+        }
+        self.insert(key.into(), self.undefined.clone());
+        Some(self.undefined.clone())
+    }
+
+    /// Get a variable that has actually been declared, without parsing `key` as a fallback
+    pub fn lookup(&self, key: &str) -> Option<Pointer> {
+        if let Some(val) = self.globals.borrow().get(key) {
+            return Some(val.clone());
+        }
+        self.find(key, |binding| binding.ptr.clone())
+    }
+
+    /// Get the history of the variable called `key`, if it's been declared and isn't a `const const const`
+    pub fn history(&self, key: &str) -> Option<RcMut<History>> {
+        if self.globals.borrow().contains_key(key) {
+            return None;
+        }
+        self.find(key, |binding| binding.history.clone())
+    }
+
+    /// Run `func` on the newest binding of `key` that hasn't expired, in this scope or a parent
+    fn find<T>(&self, key: &str, func: impl FnOnce(&Binding) -> T) -> Option<T> {
+        let clock = self.clock.borrow();
+        let live = self.current.get(key).and_then(|bindings| {
+            bindings
+                .iter()
+                .rev()
+                .find(|b| !b.is_expired(&clock, self.line))
+        });
+        match (live, &self.parent) {
+            (Some(binding), _) => Some(func(binding)),
+            (None, Some(parent)) => parent.borrow().find(key, func),
+            (None, None) => None,
+        }
+    }
+
+    /// Declare a `const const` function written in Rust. It gets called with its arguments already evaluated.
+    pub fn register_native<F: Fn(&[Pointer]) -> SResult<Pointer> + 'static>(
+        &mut self,
+        name: &str,
+        func: F,
+    ) {
+        let native = Value::Native(NativeFunction::new(name, func));
+        self.insert(name.into(), Pointer::from(native));
+    }
+
+    /// Declare a `const const const` variable, which every state sharing this one's globals can see
+    ///
+    /// # Errors
+    /// Returns `Error::Redeclared` if there's already one called `k`
+    pub fn insert_global(&self, k: Rc<str>, v: Pointer) -> Result<(), Error> {
+        let mut globals = self.globals.borrow_mut();
+        if globals.contains_key(&k) {
+            return Err(Error::Redeclared(k));
+        }
+        globals.insert(k, v);
+        Ok(())
+    }
+
     pub fn insert(&mut self, k: Rc<str>, v: Pointer) {
-        self.current.insert(k, v);
+        self.insert_with_lifetime(k, v, None);
+    }
+
+    /// Declare a variable that goes back to what it shadows once its lifetime is up
+    pub fn insert_with_lifetime(&mut self, k: Rc<str>, v: Pointer, lifetime: Option<Lifetime>) {
+        let expires = {
+            let clock = self.clock.borrow();
+            match lifetime {
+                // it lasts for the rest of the line it's declared on, and that many lines after it
+                Some(Lifetime::Lines(lines)) if lines >= 0 => {
+                    let lines = usize::try_from(lines).unwrap_or(usize::MAX);
+                    Some(Expiry::Line(
+                        self.line.saturating_add(lines).saturating_add(1),
+                    ))
+                }
+                // so long that it would never run out anyway
+                Some(Lifetime::Time(time)) => clock.elapsed().checked_add(time).map(Expiry::Time),
+                // negative lifetimes were already moved to earlier lines, so they last from there on
+                Some(Lifetime::Lines(_) | Lifetime::Infinity) | None => None,
+            }
+        };
+        let binding = Binding::new(v, expires);
+        // anything waiting for the old declaration to change waits for this one instead
+        if let Some(old) = self.current.get(&k).and_then(|bindings| {
+            let clock = self.clock.borrow();
+            bindings
+                .iter()
+                .rev()
+                .find(|b| !b.is_expired(&clock, self.line))
+        }) {
+            binding
+                .history
+                .borrow_mut()
+                .inherit(&mut old.history.borrow_mut());
+        }
+        if expires.is_none() {
+            // nothing under it could ever come back
+            self.current.insert(k, vec![binding]);
+            return;
+        }
+        let bindings = self.current.entry(k).or_default();
+        bindings.retain(|binding| !binding.is_expired(&self.clock.borrow(), self.line));
+        bindings.push(binding);
     }
 
     pub fn delete(&mut self, k: Rc<str>) {
         match self.current.entry(k.clone()) {
             std::collections::hash_map::Entry::Occupied(mut e) => {
-                e.insert(self.undefined.clone());
+                e.insert(vec![Binding::new(self.undefined.clone(), None)]);
             }
             std::collections::hash_map::Entry::Vacant(e) => {
                 if let Some(parent) = &self.parent {
                     parent.borrow_mut().delete(k);
                 } else {
-                    e.insert(self.undefined.clone());
+                    e.insert(vec![Binding::new(self.undefined.clone(), None)]);
                 }
             }
         }
@@ -1,18 +1,93 @@
-use std::{fmt::Display, rc::Rc};
+use std::{fmt::Display, rc::Rc, time::Duration};
 
-use super::StringSegment;
+use super::{Error, Span, StringSegment};
 
-#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+#[derive(PartialEq, Eq, Debug, Hash, Clone, PartialOrd, Ord)]
 pub enum Syntax {
-    Declare(VarType, Rc<str>, Box<Syntax>),
-    Function(Vec<Rc<str>>, Box<Syntax>),
-    Call(Rc<str>, Vec<Syntax>),
-    Operation(Box<Syntax>, Operation, Box<Syntax>),
+    Declare(VarType, Rc<str>, Option<Lifetime>, Box<Self>, Span),
+    Function(Vec<Rc<str>>, Box<Self>),
+    Call(Box<Self>, Vec<Self>, Span),
+    Operation(Box<Self>, Operation, Box<Self>, Span),
     Ident(Rc<str>),
     String(Vec<StringSegment>),
-    Block(Vec<Syntax>),
-    Statement(bool, Box<Syntax>, u8),
-    Negate(Box<Syntax>),
+    Block(Vec<Self>),
+    Statement(bool, Box<Self>, u8),
+    /// A statement in a block, and the source line it starts on
+    Line(usize, Box<Self>),
+    Negate(Box<Self>),
+    Increment(bool, Box<Self>),
+    Decrement(bool, Box<Self>),
+    Array(Vec<Self>),
+    Object(Vec<(Rc<str>, Self)>),
+    Index(Box<Self>, Box<Self>, Span),
+    Export(Rc<str>, Box<Self>),
+    Import(Rc<str>, Span),
+}
+
+impl Syntax {
+    /// Get the parameter names out of a function's input: either one identifier or a tuple of them
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidParameter` if any of the parameters isn't an identifier
+    pub fn into_params(self) -> Result<Vec<Rc<str>>, Error> {
+        let params = match self {
+            Self::Block(params) => params,
+            other => vec![other],
+        };
+        params
+            .into_iter()
+            .map(|syn| match syn {
+                Self::Ident(ident) => Ok(ident),
+                other => Err(Error::InvalidParameter(other.to_string().into())),
+            })
+            .collect()
+    }
+
+    /// Get every variable this reads when it's run. Field names after `.` and the insides of functions don't count.
+    pub fn idents(&self) -> Vec<&Rc<str>> {
+        let mut idents = Vec::new();
+        self.push_idents(&mut idents);
+        idents
+    }
+
+    fn push_idents<'a>(&'a self, idents: &mut Vec<&'a Rc<str>>) {
+        match self {
+            Self::Ident(ident) => idents.push(ident),
+            Self::String(segments) => {
+                idents.extend(segments.iter().filter_map(|segment| match segment {
+                    StringSegment::Ident(ident) => Some(ident),
+                    StringSegment::String(_) => None,
+                }));
+            }
+            Self::Operation(lhs, Operation::Dot, _, _) => lhs.push_idents(idents),
+            Self::Operation(lhs, _, rhs, _) | Self::Index(lhs, rhs, _) => {
+                lhs.push_idents(idents);
+                rhs.push_idents(idents);
+            }
+            Self::Call(func, args, _) => {
+                func.push_idents(idents);
+                for arg in args {
+                    arg.push_idents(idents);
+                }
+            }
+            Self::Block(items) | Self::Array(items) => {
+                for item in items {
+                    item.push_idents(idents);
+                }
+            }
+            Self::Object(fields) => fields
+                .iter()
+                .for_each(|(_, value)| value.push_idents(idents)),
+            Self::Declare(_, _, _, inner, _)
+            | Self::Statement(_, inner, _)
+            | Self::Line(_, inner)
+            | Self::Negate(inner)
+            | Self::Increment(_, inner)
+            | Self::Decrement(_, inner)
+            | Self::Export(_, inner) => inner.push_idents(idents),
+            Self::Function(..) | Self::Import(..) => {}
+        }
+    }
 }
 
 impl Display for Syntax {
@@ -25,14 +100,14 @@ impl Display for Syntax {
                     if *is_debug { "?" } else { "!" }.repeat(*count as usize)
                 )
             }
-            Self::Call(func, args) => {
+            Self::Line(_, content) => write!(f, "{content}"),
+            Self::Call(func, args, _) => {
                 write!(f, "{func}(")?;
-                let arglen = args.len() - 1;
                 for (idx, arg) in args.iter().enumerate() {
-                    write!(f, "{arg}")?;
-                    if idx != arglen {
+                    if idx != 0 {
                         write!(f, ", ")?;
                     }
+                    write!(f, "{arg}")?;
                 }
                 write!(f, ")")
             }
@@ -51,28 +126,59 @@ impl Display for Syntax {
                 write!(f, "\"")
             }
             Self::Ident(ident) => write!(f, "{ident}"),
-            Self::Declare(var_type, name, value) => {
+            Self::Declare(var_type, name, Some(lifetime), value, _) => {
+                write!(f, "{var_type} {name}{lifetime} = {value}")
+            }
+            Self::Declare(var_type, name, None, value, _) => {
                 write!(f, "{var_type} {name} = {value}")
             }
-            Self::Operation(lhs, op, rhs) => {
+            Self::Operation(lhs, op, rhs, _) => {
                 write!(f, "({lhs}{op}{rhs})")
             }
             Self::Function(args, body) => {
                 write!(f, "{args:?} -> {body}")
             }
             Self::Negate(inner) => write!(f, ";{inner}"),
+            Self::Increment(true, inner) => write!(f, "++{inner}"),
+            Self::Increment(false, inner) => write!(f, "{inner}++"),
+            Self::Decrement(true, inner) => write!(f, "--{inner}"),
+            Self::Decrement(false, inner) => write!(f, "{inner}--"),
+            Self::Array(items) => {
+                write!(f, "[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Self::Object(fields) => {
+                write!(f, "{{")?;
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                write!(f, "}}")
+            }
+            Self::Index(target, idx, _) => write!(f, "{target}[{idx}]"),
+            Self::Export(name, target) => write!(f, "export {name} to {target}"),
+            Self::Import(name, _) => write!(f, "import {name}"),
             // other => write!(f, "{other:?}"),
         }
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy, PartialOrd, Ord)]
 pub enum VarType {
     ConstConst,
     ConstVar,
     VarConst,
     VarVar,
-    // ConstConstConst,
+    /// Immutable, and visible everywhere in every file for as long as the interpreter is around
+    ConstConstConst,
 }
 
 impl Display for VarType {
@@ -82,11 +188,32 @@ impl Display for VarType {
             Self::ConstVar => write!(f, "const var"),
             Self::VarConst => write!(f, "var const"),
             Self::VarVar => write!(f, "var var"),
+            Self::ConstConstConst => write!(f, "const const const"),
+        }
+    }
+}
+
+/// How long a variable lasts before it goes back to what it was: `name<2>`, `name<20s>`, or `name<Infinity>`
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum Lifetime {
+    /// This many lines after the one it was declared on. If it's negative, it's declared that many lines earlier instead,
+    /// and lasts from then on.
+    Lines(i64),
+    Time(Duration),
+    Infinity,
+}
+
+impl Display for Lifetime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Lines(lines) => write!(f, "<{lines}>"),
+            Self::Time(time) => write!(f, "<{}s>", time.as_secs_f64()),
+            Self::Infinity => write!(f, "<Infinity>"),
         }
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy, PartialOrd, Ord)]
 pub enum Operation {
     Equal(u8),
     Add,
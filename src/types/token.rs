@@ -15,7 +15,9 @@ impl Display for StringSegment {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// A piece of source code, as the lexer splits it up
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum Token {
     Ident(Rc<str>),
     String(Vec<StringSegment>),
@@ -52,4 +54,6 @@ pub enum Token {
     Dot,
     And,
     Or,
+    /// A line of `=====` that starts a new file, with its name if it has one
+    File(Option<Rc<str>>),
 }
@@ -1,13 +1,15 @@
 use std::{
+    cell::RefCell,
     cmp::Ordering,
-    collections::BTreeMap,
-    fmt::Display,
+    collections::{BTreeMap, HashSet},
+    fmt::{Debug, Display},
     hash::Hash,
     ops::{Add, BitAnd, BitOr, Div, Mul, Neg, Rem, Sub},
+    ptr,
     rc::Rc,
 };
 
-use super::{Pointer, Syntax};
+use super::{Pointer, RcMut, SResult, State, Syntax};
 
 #[derive(PartialEq, Eq, Debug, Hash, Clone, Copy, PartialOrd, Ord)]
 pub enum Boolean {
@@ -42,9 +44,11 @@ pub enum Value {
     Boolean(Boolean),
     String(Rc<str>),
     Number(f64),
-    Object(BTreeMap<Value, Pointer>),
-    Function(Vec<Rc<str>>, Syntax),
+    Object(BTreeMap<Self, Pointer>),
+    Array(Vec<Pointer>),
+    Function(Function),
     Keyword(Keyword),
+    Native(NativeFunction),
 }
 
 impl Eq for Value {}
@@ -57,35 +61,88 @@ impl Default for Value {
 
 impl PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match unsafe {
-            core::mem::transmute::<_, u64>(core::mem::discriminant(self)).cmp(
-                &core::mem::transmute::<_, u64>(core::mem::discriminant(other)),
-            )
-        } {
-            Ordering::Equal => {}
-            other => return Some(other),
-        }
-        match (self, other) {
-            (Self::Number(lhs), Self::Number(rhs)) => lhs.partial_cmp(rhs),
-            (Self::String(lhs), Self::String(rhs)) => lhs.partial_cmp(rhs),
-            (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs.partial_cmp(rhs),
-            (Self::Keyword(lhs), Self::Keyword(rhs)) => lhs.partial_cmp(rhs),
-            _ => todo!(),
-        }
+        Some(self.cmp(other))
+    }
+}
+
+thread_local! {
+    /// The pairs of containers being compared, and the containers being printed, right now
+    static COMPARING: RefCell<HashSet<(usize, usize)>> = RefCell::new(HashSet::new());
+    static EQUATING: RefCell<HashSet<(usize, usize)>> = RefCell::new(HashSet::new());
+    static DISPLAYING: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+}
+
+/// Run `func` unless `key` is already being visited further up, so a container that holds itself isn't gone through
+/// forever. Gives `None` if it's already being visited.
+fn visit<K: Hash + Eq + Copy, T>(
+    visiting: &'static std::thread::LocalKey<RefCell<HashSet<K>>>,
+    key: K,
+    func: impl FnOnce() -> T,
+) -> Option<T> {
+    if !visiting.with_borrow_mut(|visiting| visiting.insert(key)) {
+        return None;
     }
+    let result = func();
+    visiting.with_borrow_mut(|visiting| visiting.remove(&key));
+    Some(result)
 }
 
+/// Get the address of a value, which identifies the container it's in
+fn addr(value: &Value) -> usize {
+    ptr::from_ref(value).addr()
+}
+
+/// Values of different types are ordered by type. `NaN` is bigger than every other number, and objects compare their
+/// entries in order. A pair of containers that's already being compared further up counts as equal.
 impl Ord for Value {
     fn cmp(&self, other: &Self) -> Ordering {
-        if let Some(ord) = self.partial_cmp(other) {
-            return ord;
-        };
-        todo!()
+        match (self, other) {
+            (Self::Number(lhs), Self::Number(rhs)) => lhs.total_cmp(rhs),
+            (Self::String(lhs), Self::String(rhs)) => lhs.cmp(rhs),
+            (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs.cmp(rhs),
+            (Self::Keyword(lhs), Self::Keyword(rhs)) => lhs.cmp(rhs),
+            (Self::Object(lhs), Self::Object(rhs)) => {
+                visit(&COMPARING, (addr(self), addr(other)), || {
+                    lhs.iter()
+                        .zip(rhs)
+                        .map(|((lk, lv), (rk, rv))| {
+                            lk.cmp(rk).then_with(|| lv.with_refs(rv, Self::cmp))
+                        })
+                        .find(|ord| ord.is_ne())
+                        .unwrap_or_else(|| lhs.len().cmp(&rhs.len()))
+                })
+                .unwrap_or(Ordering::Equal)
+            }
+            (Self::Array(lhs), Self::Array(rhs)) => {
+                visit(&COMPARING, (addr(self), addr(other)), || {
+                    lhs.iter()
+                        .zip(rhs)
+                        .map(|(l, r)| l.with_refs(r, Self::cmp))
+                        .find(|ord| ord.is_ne())
+                        .unwrap_or_else(|| lhs.len().cmp(&rhs.len()))
+                })
+                .unwrap_or(Ordering::Equal)
+            }
+            (Self::Function(lhs), Self::Function(rhs)) => lhs.cmp(rhs),
+            (Self::Native(lhs), Self::Native(rhs)) => lhs.cmp(rhs),
+            _ => self.type_order().cmp(&other.type_order()),
+        }
     }
 }
 
+/// A container inside of itself is printed as `[circular]`
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if matches!(self, Self::Object(_) | Self::Array(_)) {
+            return visit(&DISPLAYING, addr(self), || self.fmt_inner(f))
+                .unwrap_or_else(|| write!(f, "[circular]"));
+        }
+        self.fmt_inner(f)
+    }
+}
+
+impl Value {
+    fn fmt_inner(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Boolean(b) => write!(f, "{b}"),
             Self::String(str) => write!(f, "{str}"),
@@ -93,14 +150,23 @@ impl Display for Value {
             Self::Object(obj) => {
                 let mut map = f.debug_struct("object");
                 for (k, v) in obj {
-                    map.field(&format!("{k}"), &format!("{v}"));
+                    map.field(&format!("{k}"), &format_args!("{v}"));
                 }
                 map.finish()
             }
-            Self::Function(args, body) => {
-                write!(f, "{args:?} -> {body}")
+            Self::Array(arr) => {
+                write!(f, "[")?;
+                for (idx, item) in arr.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
             }
+            Self::Function(func) => write!(f, "{func}"),
             Self::Keyword(kw) => write!(f, "{kw}"),
+            Self::Native(native) => write!(f, "{native}"),
         }
     }
 }
@@ -120,11 +186,10 @@ impl Hash for Value {
                     v.hash(state);
                 }
             }
-            Self::Function(inputs, content) => {
-                inputs.hash(state);
-                content.hash(state);
-            }
+            Self::Array(arr) => arr.hash(state),
+            Self::Function(func) => func.hash(state),
             Self::Keyword(keyword) => keyword.hash(state),
+            Self::Native(native) => native.hash(state),
         }
     }
 }
@@ -167,6 +232,9 @@ impl Sub for Value {
     }
 }
 
+/// The longest string, in bytes, that multiplying a string can make
+const MAX_STRING_LEN: usize = 1 << 28;
+
 impl Mul for Value {
     type Output = Self;
     #[allow(
@@ -178,11 +246,19 @@ impl Mul for Value {
         match (self, rhs) {
             (Self::Number(lhs), Self::Number(rhs)) => Self::Number(lhs * rhs),
             (Self::String(str), Self::Number(num)) => {
-                let mut str_buf = str.repeat(num.abs().floor() as usize);
-                let portion = ((num.abs() - num.abs().floor()) * str.len() as f64) as usize;
-                if portion > 0 {
-                    str_buf.push_str(&str[0..portion]);
+                // there's no such thing as an infinitely long string, or one too big to fit in memory
+                let count = num.abs().floor() as usize;
+                let too_long = str
+                    .len()
+                    .checked_mul(count.saturating_add(1))
+                    .is_none_or(|len| len > MAX_STRING_LEN);
+                if !num.is_finite() || too_long {
+                    return Self::default();
                 }
+                let mut str_buf = str.repeat(count);
+                let len = str.chars().count();
+                let portion = ((num.abs() - num.abs().floor()) * len as f64) as usize;
+                str_buf.extend(str.chars().take(portion));
                 if num.is_sign_negative() {
                     str_buf = str_buf.chars().rev().collect();
                 }
@@ -262,6 +338,7 @@ impl BitOr for Value {
 }
 
 impl Value {
+    #[must_use]
     pub fn eq(&self, rhs: &Self, precision: u8) -> Self {
         if precision <= 2 && self.bool() == Boolean::False && rhs.bool() == Boolean::False {
             return Self::from(true);
@@ -281,26 +358,41 @@ impl Value {
         {
             return Self::from(true);
         }
+        let pair = (addr(self), addr(rhs));
         match (self, rhs) {
             (&Self::Number(lhs), &Self::Number(rhs)) => {
                 Self::from(lhs == rhs || (precision == 1 && (lhs / rhs).ln().abs() < 0.1))
             }
             (Self::String(lhs), Self::String(rhs)) => Self::from(*lhs == *rhs),
             (&Self::Keyword(lhs), Self::Keyword(rhs)) => Self::from(lhs == *rhs),
+            (Self::Native(lhs), Self::Native(rhs)) => Self::from(lhs == rhs),
             (Self::String(ref str), &Self::Number(num))
             | (&Self::Number(num), Self::String(ref str)) => {
                 let Ok(str_parse) = str.parse::<f64>() else {
-                    return Self::from(false)
+                    return Self::from(false);
                 };
                 Self::from(
                     num == str_parse || (precision == 1 && (num / str_parse).ln().abs() < 0.1),
                 )
             }
+            // a pair of containers that's already being checked further up counts as equal
             (Self::Object(lhs), Self::Object(rhs)) => Self::from(
-                !lhs.iter().any(|(k, v)| {
-                    rhs.get(k)
-                        .map_or(true, |r| r.eq(v, precision) == Self::from(false))
-                }) && !rhs.iter().any(|(k, _)| lhs.get(k).is_none()),
+                visit(&EQUATING, pair, || {
+                    !lhs.iter().any(|(k, v)| {
+                        rhs.get(k)
+                            .is_none_or(|r| r.eq(v, precision) == Self::from(false))
+                    }) && !rhs.iter().any(|(k, _)| lhs.get(k).is_none())
+                })
+                .unwrap_or(true),
+            ),
+            (Self::Array(lhs), Self::Array(rhs)) => Self::from(
+                lhs.len() == rhs.len()
+                    && visit(&EQUATING, pair, || {
+                        lhs.iter()
+                            .zip(rhs)
+                            .all(|(l, r)| l.eq(r, precision) != Self::from(false))
+                    })
+                    .unwrap_or(true),
             ),
             _ => Self::from(false),
         }
@@ -332,10 +424,31 @@ impl Value {
                     Boolean::True
                 }
             }
+            Self::Array(arr) => {
+                if arr.is_empty() {
+                    Boolean::False
+                } else {
+                    Boolean::True
+                }
+            }
             _ => Boolean::Maybe,
         }
     }
 
+    /// The order of each type when comparing values of different types
+    const fn type_order(&self) -> u8 {
+        match self {
+            Self::Boolean(_) => 0,
+            Self::String(_) => 1,
+            Self::Number(_) => 2,
+            Self::Object(_) => 3,
+            Self::Function(..) => 4,
+            Self::Keyword(_) => 5,
+            Self::Native(_) => 6,
+            Self::Array(_) => 7,
+        }
+    }
+
     pub const fn empty_object() -> Self {
         Self::Object(BTreeMap::new())
     }
@@ -359,6 +472,12 @@ impl From<Rc<str>> for Value {
     }
 }
 
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::String(value.into())
+    }
+}
+
 impl From<Keyword> for Value {
     fn from(value: Keyword) -> Self {
         Self::Keyword(value)
@@ -371,14 +490,60 @@ impl From<Boolean> for Value {
     }
 }
 
+/// Get the number out of a `Value::Number`, or give back the value if it's something else
+impl TryFrom<Value> for f64 {
+    type Error = Value;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(num) => Ok(num),
+            other => Err(other),
+        }
+    }
+}
+
+/// Get the string out of a `Value::String`, or give back the value if it's something else
+impl TryFrom<Value> for Rc<str> {
+    type Error = Value;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(str) => Ok(str),
+            other => Err(other),
+        }
+    }
+}
+
+/// Get the boolean out of a `Value::Boolean`, or give back the value if it's something else
+impl TryFrom<Value> for Boolean {
+    type Error = Value;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Boolean(bool) => Ok(bool),
+            other => Err(other),
+        }
+    }
+}
+
+/// A built-in that's called like a function, but gets its arguments before they're evaluated
 #[derive(PartialEq, Eq, Debug, Hash, Clone, Copy, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum Keyword {
+    Break,
     Const,
+    Continue,
+    Current,
     Delete,
     Eval,
     Function,
     If,
+    Import,
+    Next,
+    Previous,
+    Return,
+    Throw,
+    Try,
     Var,
+    When,
+    While,
 }
 
 impl Display for Keyword {
@@ -390,6 +555,178 @@ impl Display for Keyword {
             Self::Function => write!(f, "function"),
             Self::If => write!(f, "if"),
             Self::Eval => write!(f, "eval"),
+            Self::Import => write!(f, "import"),
+            Self::While => write!(f, "while"),
+            Self::Break => write!(f, "break"),
+            Self::Continue => write!(f, "continue"),
+            Self::Return => write!(f, "return"),
+            Self::Throw => write!(f, "throw"),
+            Self::Try => write!(f, "try"),
+            Self::Previous => write!(f, "previous"),
+            Self::Current => write!(f, "current"),
+            Self::Next => write!(f, "next"),
+            Self::When => write!(f, "when"),
         }
     }
 }
+
+type NativeFn = dyn Fn(&[Pointer]) -> SResult<Pointer>;
+
+/// A function written in Rust, which DreamBerd code can call like any other function
+#[derive(Clone)]
+pub struct NativeFunction {
+    name: Rc<str>,
+    func: Rc<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new<F: Fn(&[Pointer]) -> SResult<Pointer> + 'static>(name: &str, func: F) -> Self {
+        Self {
+            name: name.into(),
+            func: Rc::new(func),
+        }
+    }
+
+    /// Call the function with already-evaluated arguments
+    ///
+    /// # Errors
+    /// Returns whatever error the function itself returns
+    pub fn call(&self, args: &[Pointer]) -> SResult<Pointer> {
+        (self.func)(args)
+    }
+
+    /// Get the address of the closure, which identifies the function
+    fn addr(&self) -> *const u8 {
+        Rc::as_ptr(&self.func).cast()
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.func, &other.func)
+    }
+}
+
+impl Eq for NativeFunction {}
+
+impl PartialOrd for NativeFunction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NativeFunction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.name, self.addr()).cmp(&(&other.name, other.addr()))
+    }
+}
+
+impl Hash for NativeFunction {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.addr().hash(state);
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl Display for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "native {}", self.name)
+    }
+}
+
+/// A function written in DreamBerd
+#[derive(PartialEq, Eq, Debug, Hash, Clone, PartialOrd, Ord)]
+pub struct Function {
+    params: Vec<Rc<str>>,
+    body: Syntax,
+    scope: Scope,
+}
+
+impl Function {
+    /// Make a function that runs `body` in a child of `state`, where it was declared
+    pub(crate) fn new(params: Vec<Rc<str>>, body: Syntax, state: RcMut<State>) -> Self {
+        Self {
+            params,
+            body,
+            scope: Scope::new(state),
+        }
+    }
+
+    /// Get the names of the function's parameters
+    pub fn params(&self) -> &[Rc<str>] {
+        &self.params
+    }
+
+    pub(crate) const fn body(&self) -> &Syntax {
+        &self.body
+    }
+
+    /// Get the state the function was declared in
+    pub(crate) const fn scope(&self) -> &RcMut<State> {
+        &self.scope.0
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} -> {}", self.params, self.body)
+    }
+}
+
+/// The state a function was declared in. Its body runs in a child of this state, so it can use the variables
+/// that were around when it was made. Functions from different scopes are different functions.
+#[derive(Clone)]
+pub struct Scope(pub RcMut<State>);
+
+impl Scope {
+    /// Close over `state`. Every scope is cleared when the interpreter is torn down, since the state usually ends up
+    /// holding the function that holds it.
+    pub fn new(state: RcMut<State>) -> Self {
+        State::capture(&state);
+        Self(state)
+    }
+
+    /// Get the address of the state, which identifies the scope
+    fn addr(&self) -> *const u8 {
+        Rc::as_ptr(&self.0).cast()
+    }
+}
+
+impl PartialEq for Scope {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Scope {}
+
+impl PartialOrd for Scope {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scope {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.addr().cmp(&other.addr())
+    }
+}
+
+impl Hash for Scope {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.addr().hash(state);
+    }
+}
+
+/// The state holds the function that holds the state, so don't print it
+impl Debug for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Scope({:p})", self.addr())
+    }
+}
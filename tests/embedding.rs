@@ -0,0 +1,59 @@
+use std::rc::Rc;
+
+use dreamberd_rs::{Error, Interpreter, Keyword, Pointer, Value, VarType};
+
+#[test]
+fn embedding() {
+    let interpreter = Interpreter::new();
+    interpreter.set_global("count", Pointer::from(2.0).convert(VarType::VarVar));
+    assert_eq!(
+        interpreter.eval_str("count += 1! count").unwrap(),
+        Value::from(3.0)
+    );
+    // declarations stay in the global state between calls
+    interpreter
+        .eval_str("const const name = `Jo`! const const greet = () -> `Hi `+name!")
+        .unwrap();
+    assert_eq!(
+        interpreter.eval_str("greet()").unwrap().clone_inner(),
+        Value::from("Hi Jo")
+    );
+    let name = interpreter.get_global("name").unwrap().clone_inner();
+    assert_eq!(Rc::<str>::try_from(name), Ok("Jo".into()));
+    assert_eq!(
+        f64::try_from(interpreter.get_global("count").unwrap().clone_inner()),
+        Ok(3.0)
+    );
+    assert_eq!(interpreter.get_global("nobody"), None);
+    assert!(matches!(
+        interpreter
+            .eval_file("does/not/exist.db")
+            .unwrap_err()
+            .error,
+        Error::Io(_, std::io::ErrorKind::NotFound)
+    ));
+}
+
+#[test]
+fn matching_values_and_errors() {
+    let interpreter = Interpreter::new();
+    let func = interpreter
+        .eval_str("(a, b) -> a + b")
+        .unwrap()
+        .clone_inner();
+    let Value::Function(func) = func else {
+        panic!("expected a function, got {func:?}");
+    };
+    assert_eq!(func.params(), [Rc::from("a"), Rc::from("b")]);
+    assert!(matches!(
+        interpreter.eval_str("if(true)!").unwrap_err().error,
+        Error::InvalidArguments(Keyword::If, args) if args == [Rc::from("true")]
+    ));
+    assert_eq!(
+        interpreter
+            .eval_str("const const f = () -> break()! f()!")
+            .unwrap_err()
+            .error,
+        Error::Misplaced(Keyword::Break)
+    );
+}